@@ -0,0 +1,8 @@
+CREATE TABLE issue_delivery_queue (
+    newsletter_issue_id UUID NOT NULL
+    REFERENCES newsletter_issues (newsletter_issue_id),
+    subscriber_email TEXT NOT NULL,
+    n_retries SMALLINT NOT NULL DEFAULT 0,
+    execute_after TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (newsletter_issue_id, subscriber_email)
+);
@@ -0,0 +1,14 @@
+CREATE TYPE header_pair AS (
+    name TEXT,
+    value BYTEA
+);
+
+CREATE TABLE idempotency (
+    username TEXT NOT NULL,
+    idempotency_key TEXT NOT NULL,
+    response_status_code SMALLINT NULL,
+    response_headers HEADER_PAIR [] NULL,
+    response_body BYTEA NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (username, idempotency_key)
+);
@@ -0,0 +1,6 @@
+CREATE TABLE sessions (
+    id TEXT NOT NULL,
+    data JSONB NOT NULL,
+    expiry_date TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (id)
+);
@@ -0,0 +1,11 @@
+CREATE TYPE subscription_status AS ENUM (
+    'pending_confirmation',
+    'confirmed',
+    'unsubscribed',
+    'bounced',
+    'complained'
+);
+
+ALTER TABLE subscriptions
+ALTER COLUMN status TYPE SUBSCRIPTION_STATUS
+USING status::SUBSCRIPTION_STATUS;
@@ -0,0 +1,3 @@
+ALTER TABLE subscription_tokens
+ADD COLUMN created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+ADD COLUMN consumed_at TIMESTAMPTZ NULL;
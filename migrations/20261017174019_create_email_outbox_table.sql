@@ -0,0 +1,10 @@
+CREATE TABLE email_outbox (
+    id UUID PRIMARY KEY,
+    recipient TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    html_content TEXT NOT NULL,
+    text_content TEXT NOT NULL,
+    n_retries SMALLINT NOT NULL DEFAULT 0,
+    execute_after TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
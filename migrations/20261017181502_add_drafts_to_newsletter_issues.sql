@@ -0,0 +1,28 @@
+CREATE TYPE newsletter_issue_status AS ENUM (
+    'draft',
+    'scheduled',
+    'sending',
+    'sent'
+);
+
+ALTER TABLE newsletter_issues
+ADD COLUMN status NEWSLETTER_ISSUE_STATUS NOT NULL DEFAULT 'sent',
+ADD COLUMN markdown_content TEXT NULL,
+ADD COLUMN author_id UUID NULL REFERENCES users (user_id) ON DELETE SET NULL,
+ADD COLUMN created_at TIMESTAMPTZ NULL,
+ADD COLUMN updated_at TIMESTAMPTZ NULL,
+ALTER COLUMN published_at DROP NOT NULL;
+
+UPDATE newsletter_issues
+SET created_at = published_at, updated_at = published_at;
+
+UPDATE newsletter_issues
+SET status = 'sending'
+WHERE newsletter_issue_id IN (SELECT newsletter_issue_id FROM issue_delivery_queue);
+
+ALTER TABLE newsletter_issues
+ALTER COLUMN status DROP DEFAULT,
+ALTER COLUMN created_at SET NOT NULL,
+ALTER COLUMN created_at SET DEFAULT NOW(),
+ALTER COLUMN updated_at SET NOT NULL,
+ALTER COLUMN updated_at SET DEFAULT NOW();
@@ -0,0 +1,6 @@
+ALTER TABLE newsletter_issues
+ADD COLUMN scheduled_for TIMESTAMPTZ NULL;
+
+CREATE INDEX newsletter_issues_scheduled_for_idx
+ON newsletter_issues (scheduled_for)
+WHERE status = 'scheduled';
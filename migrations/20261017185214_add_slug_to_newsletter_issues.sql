@@ -0,0 +1,14 @@
+ALTER TABLE newsletter_issues
+ADD COLUMN slug TEXT NULL;
+
+-- Mirrors NewsletterIssue::slug_for.
+UPDATE newsletter_issues
+SET slug = CONCAT_WS(
+    '-',
+    NULLIF(TRIM(BOTH '-' FROM REGEXP_REPLACE(LOWER(title), '[^a-z0-9]+', '-', 'g')), ''),
+    LEFT(REPLACE(newsletter_issue_id::TEXT, '-', ''), 8)
+);
+
+ALTER TABLE newsletter_issues
+ALTER COLUMN slug SET NOT NULL,
+ADD CONSTRAINT newsletter_issues_slug_key UNIQUE (slug);
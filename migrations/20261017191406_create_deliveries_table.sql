@@ -0,0 +1,25 @@
+CREATE TYPE delivery_status AS ENUM (
+    'pending',
+    'sent',
+    'failed',
+    'skipped'
+);
+
+CREATE TABLE deliveries (
+    newsletter_issue_id UUID NOT NULL
+    REFERENCES newsletter_issues (newsletter_issue_id),
+    subscriber_email TEXT NOT NULL,
+    status DELIVERY_STATUS NOT NULL DEFAULT 'pending',
+    n_attempts SMALLINT NOT NULL DEFAULT 0,
+    provider_message_id TEXT NULL,
+    last_error TEXT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (newsletter_issue_id, subscriber_email)
+);
+
+-- Deliveries still in the queue when this migration runs are tracked from
+-- here on, earlier ones were never recorded.
+INSERT INTO deliveries (newsletter_issue_id, subscriber_email, n_attempts)
+SELECT newsletter_issue_id, subscriber_email, n_retries
+FROM issue_delivery_queue;
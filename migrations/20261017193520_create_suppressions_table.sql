@@ -0,0 +1,27 @@
+CREATE TYPE suppression_reason AS ENUM (
+    'bounce',
+    'complaint',
+    'unsubscribe',
+    'manual'
+);
+
+-- Addresses are stored lowercase, so lookups are case-insensitive.
+CREATE TABLE suppressions (
+    address TEXT PRIMARY KEY,
+    reason SUPPRESSION_REASON NOT NULL,
+    -- Who reported the address, e.g. the email provider or the admin user.
+    source TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO suppressions (address, reason, source)
+SELECT DISTINCT ON (LOWER(email))
+    LOWER(email),
+    CASE status
+        WHEN 'bounced' THEN 'bounce'::SUPPRESSION_REASON
+        WHEN 'complained' THEN 'complaint'::SUPPRESSION_REASON
+        ELSE 'unsubscribe'::SUPPRESSION_REASON
+    END,
+    'subscriptions'
+FROM subscriptions
+WHERE status IN ('bounced', 'complained', 'unsubscribed');
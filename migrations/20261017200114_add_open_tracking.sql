@@ -0,0 +1,7 @@
+-- Open tracking is opt-in, issues only carry a tracking pixel if their author
+-- asked for one.
+ALTER TABLE newsletter_issues ADD COLUMN track_opens BOOLEAN NOT NULL DEFAULT FALSE;
+
+ALTER TABLE deliveries ADD COLUMN first_opened_at TIMESTAMPTZ;
+-- Includes repeat opens and any image prefetching done by the mail client.
+ALTER TABLE deliveries ADD COLUMN n_opens INTEGER NOT NULL DEFAULT 0;
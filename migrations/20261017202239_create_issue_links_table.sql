@@ -0,0 +1,13 @@
+-- The URLs linked from an issue, recorded when it is published. Click
+-- tracking redirects to these and nowhere else.
+CREATE TABLE issue_links (
+    link_id UUID PRIMARY KEY,
+    newsletter_issue_id UUID NOT NULL
+    REFERENCES newsletter_issues (newsletter_issue_id),
+    url TEXT NOT NULL,
+    n_clicks INTEGER NOT NULL DEFAULT 0,
+    UNIQUE (newsletter_issue_id, url)
+);
+
+ALTER TABLE deliveries ADD COLUMN first_clicked_at TIMESTAMPTZ;
+ALTER TABLE deliveries ADD COLUMN n_clicks INTEGER NOT NULL DEFAULT 0;
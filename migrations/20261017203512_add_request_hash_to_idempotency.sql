@@ -0,0 +1,2 @@
+-- Keys saved before this column existed are replayed without the check.
+ALTER TABLE idempotency ADD COLUMN request_hash BYTEA NULL;
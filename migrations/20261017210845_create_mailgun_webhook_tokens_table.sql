@@ -0,0 +1,6 @@
+-- Tokens of the Mailgun webhooks already handled, so a signed payload cannot
+-- be replayed. They are kept until their signature would be too old anyway.
+CREATE TABLE mailgun_webhook_tokens (
+    token TEXT PRIMARY KEY,
+    expires_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,4 @@
+-- Click tracking is opt-in like open tracking. Issues sent before this column
+-- existed had their links tracked, so they keep their click statistics.
+ALTER TABLE newsletter_issues ADD COLUMN track_clicks BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE newsletter_issues ALTER COLUMN track_clicks SET DEFAULT FALSE;
@@ -0,0 +1,75 @@
+use axum::http::HeaderMap;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use secrecy::SecretString;
+
+use super::{AuthError, Credentials};
+
+pub fn basic_authentication(headers: &HeaderMap) -> Result<Credentials, AuthError> {
+    let header_value = headers
+        .get("Authorization")
+        .ok_or_else(|| AuthError::MissingCredentials("missing authorization header".into()))?
+        .to_str()
+        .map_err(|_| AuthError::MissingCredentials("authorization header is not UTF8".into()))?;
+    let base64_encoded_segment = header_value.strip_prefix("Basic ").ok_or_else(|| {
+        AuthError::MissingCredentials("authorization scheme was not `Basic`".into())
+    })?;
+    let decoded_bytes = STANDARD
+        .decode(base64_encoded_segment)
+        .map_err(|_| AuthError::MissingCredentials("failed to decode credentials".into()))?;
+    let decoded_credentials = String::from_utf8(decoded_bytes)
+        .map_err(|_| AuthError::MissingCredentials("credentials are not UTF8".into()))?;
+
+    let (username, password) = decoded_credentials.split_once(':').ok_or_else(|| {
+        AuthError::MissingCredentials("credentials must be in the `username:password` form".into())
+    })?;
+
+    Ok(Credentials {
+        username: username.to_owned(),
+        password: SecretString::from(password),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+    use claims::assert_err;
+    use secrecy::ExposeSecret;
+
+    use super::*;
+
+    fn headers(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "Authorization",
+            HeaderValue::from_str(value).expect("invalid header value"),
+        );
+        headers
+    }
+
+    #[test]
+    fn basic_credentials_are_decoded() {
+        let encoded = STANDARD.encode("ursula:earthsea");
+        let credentials = basic_authentication(&headers(&format!("Basic {encoded}")))
+            .expect("failed to decode credentials");
+
+        assert_eq!(credentials.username, "ursula");
+        assert_eq!(credentials.password.expose_secret(), "earthsea");
+    }
+
+    #[test]
+    fn a_missing_header_is_rejected() {
+        assert_err!(basic_authentication(&HeaderMap::new()));
+    }
+
+    #[test]
+    fn a_non_basic_scheme_is_rejected() {
+        assert_err!(basic_authentication(&headers("Bearer token")));
+    }
+
+    #[test]
+    fn credentials_without_a_colon_are_rejected() {
+        let encoded = STANDARD.encode("ursula");
+        assert_err!(basic_authentication(&headers(&format!("Basic {encoded}"))));
+    }
+}
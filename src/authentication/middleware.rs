@@ -0,0 +1,42 @@
+use std::ops::Deref;
+
+use axum::extract::Request;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Redirect, Response};
+use uuid::Uuid;
+
+use crate::error::Result;
+use crate::session_state::TypedSession;
+
+#[derive(Clone, Copy, Debug)]
+pub struct UserId(Uuid);
+
+impl std::fmt::Display for UserId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Deref for UserId {
+    type Target = Uuid;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Redirects requests without a logged-in user to the login form, otherwise
+/// makes the user's id available to handlers as an `Extension<UserId>`.
+pub async fn reject_anonymous_users(
+    session: TypedSession,
+    mut request: Request,
+    next: Next,
+) -> Result<Response> {
+    match session.get_user_id().await? {
+        Some(user_id) => {
+            request.extensions_mut().insert(UserId(user_id));
+            Ok(next.run(request).await)
+        }
+        None => Ok(Redirect::to("/login").into_response()),
+    }
+}
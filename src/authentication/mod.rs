@@ -0,0 +1,10 @@
+mod basic;
+mod middleware;
+mod password;
+
+pub use basic::basic_authentication;
+pub use middleware::{UserId, reject_anonymous_users};
+pub use password::{
+    AuthError, Credentials, change_password, compute_password_hash, seed_admin,
+    validate_credentials,
+};
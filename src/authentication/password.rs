@@ -0,0 +1,212 @@
+use argon2::password_hash::SaltString;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::{Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version};
+use secrecy::{ExposeSecret, SecretString};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::configuration::AdminSettings;
+use crate::error::HttpError;
+use crate::telemetry::spawn_blocking_with_tracing;
+
+/// Verified against when the username is unknown, so that a failed lookup
+/// costs as much as a wrong password and usernames cannot be enumerated.
+const FALLBACK_PASSWORD_HASH: &str = "$argon2id$v=19$m=15000,t=2,p=1$\
+gZiV/M1gPc22ElAH/Jh1Hw$CWOrkoo7oJBQ/iyh7uJ0LO2aLEfrHwTWllSAxT0zRno";
+
+#[derive(Debug)]
+pub struct Credentials {
+    pub username: String,
+    pub password: SecretString,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error("invalid credentials")]
+    InvalidCredentials,
+    #[error("missing credentials: {0}")]
+    MissingCredentials(String),
+    #[error("unexpected error: {0}")]
+    UnexpectedError(String),
+}
+
+impl From<AuthError> for HttpError {
+    fn from(err: AuthError) -> Self {
+        match err {
+            AuthError::InvalidCredentials | AuthError::MissingCredentials(_) => {
+                Self::AuthorizationError(err.to_string())
+            }
+            AuthError::UnexpectedError(e) => {
+                tracing::error!("authentication failed unexpectedly: {e}");
+                Self::UnexpectedError
+            }
+        }
+    }
+}
+
+#[tracing::instrument(name = "validate credentials", skip_all, fields(username = %credentials.username))]
+pub async fn validate_credentials(
+    pool: &PgPool,
+    credentials: Credentials,
+) -> Result<Uuid, AuthError> {
+    let (user_id, expected_password_hash) =
+        match get_stored_credentials(pool, &credentials.username).await? {
+            Some((user_id, password_hash)) => (Some(user_id), password_hash),
+            None => (None, SecretString::from(FALLBACK_PASSWORD_HASH)),
+        };
+
+    spawn_blocking_with_tracing(move || {
+        verify_password_hash(&expected_password_hash, &credentials.password)
+    })
+    .await
+    .map_err(|e| AuthError::UnexpectedError(format!("failed to spawn a blocking task: {e}")))??;
+
+    user_id.ok_or(AuthError::InvalidCredentials)
+}
+
+#[tracing::instrument(name = "get stored credentials", skip_all)]
+async fn get_stored_credentials(
+    pool: &PgPool,
+    username: &str,
+) -> Result<Option<(Uuid, SecretString)>, AuthError> {
+    let row = sqlx::query!(
+        r#"
+        SELECT user_id, password_hash
+        FROM users
+        WHERE username = $1
+        "#,
+        username,
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| AuthError::UnexpectedError(format!("failed to query stored credentials: {e}")))?
+    .map(|row| (row.user_id, SecretString::from(row.password_hash)));
+
+    Ok(row)
+}
+
+#[tracing::instrument(name = "verify password hash", skip_all)]
+fn verify_password_hash(
+    expected_password_hash: &SecretString,
+    password_candidate: &SecretString,
+) -> Result<(), AuthError> {
+    let expected_password_hash = PasswordHash::new(expected_password_hash.expose_secret())
+        .map_err(|e| AuthError::UnexpectedError(format!("failed to parse hash: {e}")))?;
+
+    Argon2::default()
+        .verify_password(
+            password_candidate.expose_secret().as_bytes(),
+            &expected_password_hash,
+        )
+        .map_err(|_| AuthError::InvalidCredentials)
+}
+
+/// Hashes `password` with Argon2id, returning the hash as a PHC string.
+pub fn compute_password_hash(password: &SecretString) -> Result<SecretString, AuthError> {
+    let salt = SaltString::generate(&mut OsRng);
+    let params = Params::new(15000, 2, 1, None)
+        .map_err(|e| AuthError::UnexpectedError(format!("invalid argon2 parameters: {e}")))?;
+    let password_hash = Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password(password.expose_secret().as_bytes(), &salt)
+        .map_err(|e| AuthError::UnexpectedError(format!("failed to hash password: {e}")))?
+        .to_string();
+    Ok(SecretString::from(password_hash))
+}
+
+#[tracing::instrument(name = "change password", skip_all)]
+pub async fn change_password(
+    pool: &PgPool,
+    user_id: Uuid,
+    password: SecretString,
+) -> Result<(), AuthError> {
+    let password_hash = spawn_blocking_with_tracing(move || compute_password_hash(&password))
+        .await
+        .map_err(|e| {
+            AuthError::UnexpectedError(format!("failed to spawn a blocking task: {e}"))
+        })??;
+
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET password_hash = $1
+        WHERE user_id = $2
+        "#,
+        password_hash.expose_secret(),
+        user_id,
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| AuthError::UnexpectedError(format!("failed to change the password: {e}")))?;
+
+    Ok(())
+}
+
+/// Creates the configured admin account, unless an operator already exists.
+/// Nothing is seeded until a password is configured, deployments set it
+/// through `APP_ADMIN__PASSWORD`.
+#[tracing::instrument(name = "seed admin user", skip_all, fields(username = %admin.username))]
+pub async fn seed_admin(pool: &PgPool, admin: &AdminSettings) -> Result<(), AuthError> {
+    if has_users(pool).await? {
+        return Ok(());
+    }
+    let Some(password) = admin.password.clone() else {
+        tracing::warn!("no admin password is configured, not seeding an admin user");
+        return Ok(());
+    };
+    let password_hash = spawn_blocking_with_tracing(move || compute_password_hash(&password))
+        .await
+        .map_err(|e| {
+            AuthError::UnexpectedError(format!("failed to spawn a blocking task: {e}"))
+        })??;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO users (user_id, username, password_hash)
+        SELECT $1, $2, $3
+        WHERE NOT EXISTS (SELECT 1 FROM users)
+        "#,
+        Uuid::new_v4(),
+        admin.username,
+        password_hash.expose_secret(),
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| AuthError::UnexpectedError(format!("failed to seed the admin user: {e}")))?;
+
+    Ok(())
+}
+
+async fn has_users(pool: &PgPool) -> Result<bool, AuthError> {
+    let row = sqlx::query!(r#"SELECT EXISTS (SELECT 1 FROM users) AS "exists!""#)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| AuthError::UnexpectedError(format!("failed to look up users: {e}")))?;
+    Ok(row.exists)
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+
+    use super::*;
+
+    #[test]
+    fn a_computed_hash_is_a_verifiable_argon2id_phc_string() {
+        let password = SecretString::from("earthsea");
+        let hash = compute_password_hash(&password).expect("failed to hash password");
+
+        assert!(
+            hash.expose_secret()
+                .starts_with("$argon2id$v=19$m=15000,t=2,p=1$")
+        );
+        assert_ok!(verify_password_hash(&hash, &password));
+    }
+
+    #[test]
+    fn a_wrong_password_is_rejected() {
+        let hash = compute_password_hash(&SecretString::from("earthsea"))
+            .expect("failed to hash password");
+
+        assert_err!(verify_password_hash(&hash, &SecretString::from("tehanu")));
+    }
+}
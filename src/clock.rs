@@ -0,0 +1,16 @@
+use sqlx::types::chrono::{DateTime, Utc};
+
+/// The source of the current time for code that acts on timestamps stored in
+/// the database, so tests can move time forward instead of sleeping.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
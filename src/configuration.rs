@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
 use secrecy::{ExposeSecret, SecretString};
@@ -6,22 +8,48 @@ use sqlx::ConnectOptions;
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
 
 use crate::domain::SubscriberEmail;
+use crate::email_client::{
+    EmailClient, FileSink, MailgunClient, MemorySink, PostmarkClient, RetryPolicy, SendGridClient,
+    SmtpClient,
+};
 
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize)]
 pub struct Settings {
+    pub admin: AdminSettings,
     pub application: ApplicationSettings,
     pub database: DatabaseSettings,
     pub email_client: EmailClientSettings,
+    pub webhooks: WebhookSettings,
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
+pub struct AdminSettings {
+    pub username: String,
+    /// Only set for local development, see `seed_admin`.
+    pub password: Option<SecretString>,
+}
+
+#[derive(Clone, Deserialize)]
 pub struct ApplicationSettings {
     pub port: u16,
     pub host: String,
     pub base_url: String,
+    pub secure_cookies: bool,
+    /// Signs unsubscribe and tracking links. Only `local.toml` sets one, other
+    /// environments must provide `APP_APPLICATION__HMAC_SECRET`.
+    pub hmac_secret: SecretString,
+    pub subscription_token_ttl_hours: u64,
+    /// Where the email templates are loaded from at startup.
+    pub templates_directory: PathBuf,
+}
+
+impl ApplicationSettings {
+    pub const fn subscription_token_ttl(&self) -> Duration {
+        Duration::from_hours(self.subscription_token_ttl_hours)
+    }
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Deserialize)]
 pub struct DatabaseSettings {
     pub username: String,
     pub password: SecretString,
@@ -53,15 +81,121 @@ impl DatabaseSettings {
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailProvider {
+    #[default]
+    Postmark,
+    Sendgrid,
+    Mailgun,
+    Smtp,
+    /// Logs emails instead of sending them.
+    Memory,
+    /// Writes emails to `sink_directory` instead of sending them.
+    File,
+}
+
+#[derive(Clone, Deserialize)]
 pub struct EmailClientSettings {
+    #[serde(default)]
+    pub provider: EmailProvider,
     pub base_url: String,
     pub sender_email: String,
     pub api_token: SecretString,
     pub timeout_milliseconds: u64,
+    /// How often the queue workers retry a transient failure before giving up,
+    /// see [`RetryPolicy`].
+    pub max_retries: u32,
+    pub retry_base_delay_milliseconds: u64,
+    pub retry_max_delay_milliseconds: u64,
+    /// Where the `file` provider writes emails to.
+    #[serde(default = "default_sink_directory")]
+    pub sink_directory: PathBuf,
+    /// Required by the `smtp` provider.
+    pub smtp: Option<SmtpSettings>,
+}
+
+fn default_sink_directory() -> PathBuf {
+    PathBuf::from("target/emails")
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpTls {
+    /// Plain text, only meant for relays on a trusted network.
+    None,
+    /// Upgrade the connection with `STARTTLS`, usually on port 587.
+    #[default]
+    Starttls,
+    /// TLS from the first byte, usually on port 465.
+    Implicit,
+}
+
+#[derive(Clone, Deserialize)]
+pub struct SmtpSettings {
+    pub host: String,
+    pub port: u16,
+    #[serde(default)]
+    pub tls: SmtpTls,
+    /// Authenticates with `AUTH PLAIN` or `AUTH LOGIN` when both are set.
+    pub username: Option<String>,
+    pub password: Option<SecretString>,
+    #[serde(default = "default_smtp_pool_max_size")]
+    pub pool_max_size: u32,
+}
+
+const fn default_smtp_pool_max_size() -> u32 {
+    10
+}
+
+/// How inbound webhooks from email providers are authenticated. Only
+/// `local.toml` sets these, other environments must provide them as
+/// `APP_WEBHOOKS__*` variables or the application refuses to start.
+#[derive(Clone, Debug, Deserialize)]
+pub struct WebhookSettings {
+    /// The basic auth credentials set on Postmark's webhook URL.
+    pub postmark_username: String,
+    pub postmark_password: SecretString,
+    /// Mailgun's HTTP webhook signing key.
+    pub mailgun_signing_key: SecretString,
 }
 
 impl EmailClientSettings {
+    pub fn client(self) -> EmailClient {
+        let sender_email = self.sender().expect("invalid sender email address");
+        let timeout = self.timeout();
+        match self.provider {
+            EmailProvider::Postmark => Arc::new(PostmarkClient::new(
+                self.base_url,
+                sender_email,
+                self.api_token,
+                timeout,
+            )),
+            EmailProvider::Sendgrid => Arc::new(SendGridClient::new(
+                self.base_url,
+                sender_email,
+                self.api_token,
+                timeout,
+            )),
+            EmailProvider::Mailgun => Arc::new(MailgunClient::new(
+                self.base_url,
+                sender_email,
+                self.api_token,
+                timeout,
+            )),
+            EmailProvider::Smtp => Arc::new(
+                SmtpClient::new(
+                    self.smtp.expect("missing smtp settings"),
+                    sender_email,
+                    timeout,
+                )
+                .expect("invalid smtp settings"),
+            ),
+            EmailProvider::Memory => Arc::new(MemorySink::new(sender_email)),
+            EmailProvider::File => Arc::new(FileSink::new(sender_email, self.sink_directory)),
+        }
+    }
+
     pub fn sender(&self) -> Result<SubscriberEmail, String> {
         SubscriberEmail::parse(self.sender_email.clone())
     }
@@ -69,6 +203,14 @@ impl EmailClientSettings {
     pub const fn timeout(&self) -> Duration {
         Duration::from_millis(self.timeout_milliseconds)
     }
+
+    pub const fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: self.max_retries,
+            base_delay: Duration::from_millis(self.retry_base_delay_milliseconds),
+            max_delay: Duration::from_millis(self.retry_max_delay_milliseconds),
+        }
+    }
 }
 
 pub fn get() -> Result<Settings, config::ConfigError> {
@@ -0,0 +1,14 @@
+/// What became of an issue for one subscriber, stored as the Postgres
+/// `delivery_status` enum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "delivery_status", rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    /// Queued, or waiting to be retried after a transient failure.
+    Pending,
+    /// Accepted by the email provider.
+    Sent,
+    /// Rejected by the email provider, or retried until we gave up.
+    Failed,
+    /// Never attempted, e.g. because the subscriber left before it went out.
+    Skipped,
+}
@@ -1,7 +1,22 @@
+mod delivery_status;
 mod new_subscriber;
+mod newsletter_content;
+mod newsletter_issue;
+mod signed_token;
 mod subscriber_email;
 mod subscriber_name;
+mod subscription_status;
+mod suppression_reason;
+mod tracking_token;
+mod unsubscribe_token;
 
+pub use delivery_status::DeliveryStatus;
 pub use new_subscriber::NewSubscriber;
+pub use newsletter_content::NewsletterContent;
+pub use newsletter_issue::{NewsletterIssue, NewsletterIssueStatus};
 pub use subscriber_email::SubscriberEmail;
 pub use subscriber_name::SubscriberName;
+pub use subscription_status::SubscriptionStatus;
+pub use suppression_reason::SuppressionReason;
+pub use tracking_token::TrackingToken;
+pub use unsubscribe_token::UnsubscribeToken;
@@ -0,0 +1,228 @@
+use std::fmt::Write;
+
+use pulldown_cmark::{CowStr, Event, Options, Parser, Tag, TagEnd};
+
+/// The HTML and plain text versions of a newsletter issue's body.
+#[derive(Debug)]
+pub struct NewsletterContent {
+    pub html: String,
+    pub text: String,
+}
+
+impl NewsletterContent {
+    /// Renders Markdown to HTML and to a plain text alternative.
+    ///
+    /// Raw HTML in the Markdown is escaped rather than passed through, and links
+    /// or images pointing anywhere but `http`, `https` or `mailto` URLs are
+    /// dropped, so the HTML is safe to send as is.
+    pub fn from_markdown(markdown: &str) -> Self {
+        let mut html = String::new();
+        pulldown_cmark::html::push_html(&mut html, parser(markdown).map(sanitize));
+
+        let mut text = TextWriter::default();
+        for event in parser(markdown) {
+            text.write(event);
+        }
+
+        Self {
+            html,
+            text: text.finish(),
+        }
+    }
+}
+
+fn parser(markdown: &str) -> Parser<'_> {
+    Parser::new_ext(markdown, Options::ENABLE_STRIKETHROUGH)
+}
+
+fn sanitize(event: Event<'_>) -> Event<'_> {
+    match event {
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) if !is_safe_url(&dest_url) => Event::Start(Tag::Link {
+            link_type,
+            dest_url: CowStr::Borrowed(""),
+            title,
+            id,
+        }),
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) if !is_safe_url(&dest_url) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: CowStr::Borrowed(""),
+            title,
+            id,
+        }),
+        event => event,
+    }
+}
+
+/// Relative URLs and `http`, `https` and `mailto` URLs, anything else (such
+/// as `javascript:`) could run code when clicked.
+fn is_safe_url(url: &str) -> bool {
+    let url = url.trim_start();
+    match url.find([':', '/', '?', '#']) {
+        Some(i) if url[i..].starts_with(':') => {
+            let scheme = url[..i].to_ascii_lowercase();
+            matches!(scheme.as_str(), "http" | "https" | "mailto")
+        }
+        _ => true,
+    }
+}
+
+/// Turns Markdown events into readable plain text: paragraphs separated by a
+/// blank line, bulleted and numbered list items, link targets in parentheses
+/// and indented code blocks.
+#[derive(Default)]
+struct TextWriter {
+    out: String,
+    /// The next number of each open list, `None` for bulleted lists.
+    lists: Vec<Option<u64>>,
+    /// The target of each open link or image.
+    links: Vec<String>,
+    in_code_block: bool,
+}
+
+impl TextWriter {
+    fn write(&mut self, event: Event<'_>) {
+        match event {
+            Event::Start(Tag::List(start)) => {
+                self.end_line();
+                self.lists.push(start);
+            }
+            Event::End(TagEnd::List(_)) => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.out.push('\n');
+                }
+            }
+            Event::Start(Tag::Item) => {
+                self.end_line();
+                let depth = self.lists.len().saturating_sub(1);
+                self.out.push_str(&"  ".repeat(depth));
+                match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        let _ = write!(self.out, "{n}. ");
+                        *n += 1;
+                    }
+                    _ => self.out.push_str("- "),
+                }
+            }
+            Event::End(TagEnd::Item) => self.end_line(),
+            Event::End(TagEnd::Paragraph | TagEnd::Heading(_)) => {
+                // Paragraphs inside list items are kept tight.
+                if self.lists.is_empty() {
+                    self.out.push_str("\n\n");
+                } else {
+                    self.end_line();
+                }
+            }
+            Event::Start(Tag::CodeBlock(_)) => {
+                self.end_line();
+                self.in_code_block = true;
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                self.in_code_block = false;
+                self.out.push('\n');
+            }
+            Event::Start(Tag::Link { dest_url, .. } | Tag::Image { dest_url, .. }) => {
+                self.links.push(dest_url.into_string());
+            }
+            Event::End(TagEnd::Link | TagEnd::Image) => {
+                let url = self.links.pop().unwrap_or_default();
+                // Autolinks already show their target.
+                if !url.is_empty() && !self.out.ends_with(&url) {
+                    let _ = write!(self.out, " ({url})");
+                }
+            }
+            Event::Text(text) if self.in_code_block => {
+                for line in text.lines() {
+                    self.out.push_str("    ");
+                    self.out.push_str(line);
+                    self.out.push('\n');
+                }
+            }
+            Event::Text(text) | Event::Code(text) | Event::Html(text) | Event::InlineHtml(text) => {
+                self.out.push_str(&text);
+            }
+            Event::SoftBreak | Event::HardBreak => self.out.push('\n'),
+            Event::Rule => {
+                self.end_line();
+                self.out.push_str("----\n\n");
+            }
+            _ => {}
+        }
+    }
+
+    fn end_line(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+    }
+
+    fn finish(self) -> String {
+        let mut text = self.out.trim_end().to_owned();
+        text.push('\n');
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NewsletterContent;
+
+    #[test]
+    fn markdown_is_rendered_to_html() {
+        let content = NewsletterContent::from_markdown("# Title\n\nSome *emphasis*.");
+
+        assert!(content.html.contains("<h1>Title</h1>"));
+        assert!(content.html.contains("<p>Some <em>emphasis</em>.</p>"));
+    }
+
+    #[test]
+    fn raw_html_is_escaped() {
+        let content =
+            NewsletterContent::from_markdown("Hello <script>alert(1)</script>\n\n<div>block</div>");
+
+        assert!(!content.html.contains("<script>"));
+        assert!(!content.html.contains("<div>"));
+        assert!(content.html.contains("&lt;script&gt;"));
+    }
+
+    #[test]
+    fn unsafe_links_are_dropped() {
+        let content = NewsletterContent::from_markdown(
+            "[click](javascript:alert(1)) [safe](https://example.com) ![img](data:image/png)",
+        );
+
+        assert!(!content.html.contains("javascript:"));
+        assert!(!content.html.contains("data:"));
+        assert!(content.html.contains(r#"href="https://example.com""#));
+    }
+
+    #[test]
+    fn a_plain_text_alternative_is_derived() {
+        let content = NewsletterContent::from_markdown(
+            "# Title\n\nRead [the post](https://example.com).\n\n- one\n- two\n\n1. first\n2. second\n",
+        );
+
+        assert_eq!(
+            content.text,
+            "Title\n\nRead the post (https://example.com).\n\n- one\n- two\n\n1. first\n2. second\n"
+        );
+    }
+
+    #[test]
+    fn code_blocks_are_indented_in_plain_text() {
+        let content = NewsletterContent::from_markdown("Run:\n\n```\ncargo test\n```\n");
+
+        assert_eq!(content.text, "Run:\n\n    cargo test\n");
+    }
+}
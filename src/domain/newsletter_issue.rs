@@ -0,0 +1,118 @@
+use sqlx::types::chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// Where an issue is in its lifecycle, stored as the Postgres
+/// `newsletter_issue_status` enum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "newsletter_issue_status", rename_all = "snake_case")]
+pub enum NewsletterIssueStatus {
+    Draft,
+    /// Waiting for its `scheduled_for` time, then published by the scheduler.
+    Scheduled,
+    /// Delivery tasks have been queued and not all of them have finished.
+    Sending,
+    Sent,
+}
+
+impl NewsletterIssueStatus {
+    /// Only drafts may be changed, anything later has (or is about to have)
+    /// reached subscribers.
+    pub const fn is_editable(self) -> bool {
+        matches!(self, Self::Draft)
+    }
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Draft => "draft",
+            Self::Scheduled => "scheduled",
+            Self::Sending => "sending",
+            Self::Sent => "sent",
+        }
+    }
+}
+
+impl std::fmt::Display for NewsletterIssueStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A newsletter issue as stored in the archive.
+#[derive(Debug)]
+pub struct NewsletterIssue {
+    pub newsletter_issue_id: Uuid,
+    pub title: String,
+    /// Identifies the issue in public archive URLs.
+    pub slug: String,
+    /// The Markdown the issue was written in, `None` if it was published
+    /// with hand-written HTML and plain text.
+    pub markdown_content: Option<String>,
+    pub html_content: String,
+    pub text_content: String,
+    /// `None` if the author's account has since been deleted.
+    pub author_id: Option<Uuid>,
+    pub status: NewsletterIssueStatus,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub published_at: Option<DateTime<Utc>>,
+    pub scheduled_for: Option<DateTime<Utc>>,
+    /// Whether deliveries carry a pixel that records when they are opened.
+    pub track_opens: bool,
+    /// Whether links in deliveries go through the click tracking redirect.
+    pub track_clicks: bool,
+}
+
+impl NewsletterIssue {
+    /// A URL-safe version of the title, lowercase ASCII words joined by
+    /// dashes, followed by the start of the issue id so that issues sharing a
+    /// title still get distinct slugs.
+    pub fn slug_for(title: &str, newsletter_issue_id: Uuid) -> String {
+        let mut slug = String::new();
+        for word in title
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|word| !word.is_empty())
+        {
+            slug.push_str(&word.to_ascii_lowercase());
+            slug.push('-');
+        }
+        let id = newsletter_issue_id.simple().to_string();
+        slug.push_str(&id[..8]);
+        slug
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::{NewsletterIssue, NewsletterIssueStatus};
+
+    #[test]
+    fn only_drafts_are_editable() {
+        assert!(NewsletterIssueStatus::Draft.is_editable());
+        for status in [
+            NewsletterIssueStatus::Scheduled,
+            NewsletterIssueStatus::Sending,
+            NewsletterIssueStatus::Sent,
+        ] {
+            assert!(!status.is_editable());
+        }
+    }
+
+    #[test]
+    fn slugs_are_made_of_the_title_words_and_the_id() {
+        let id = Uuid::parse_str("3f2a9c1b-0000-4000-8000-000000000000").expect("valid uuid");
+
+        assert_eq!(
+            NewsletterIssue::slug_for("Issue #1: What's new?", id),
+            "issue-1-what-s-new-3f2a9c1b"
+        );
+    }
+
+    #[test]
+    fn titles_without_ascii_words_still_get_a_slug() {
+        let id = Uuid::parse_str("3f2a9c1b-0000-4000-8000-000000000000").expect("valid uuid");
+
+        assert_eq!(NewsletterIssue::slug_for("¡¿…?!", id), "3f2a9c1b");
+    }
+}
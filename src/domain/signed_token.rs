@@ -0,0 +1,117 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, SecretString};
+use sha2::Sha256;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Encodes `ids` as `<payload>.<signature>`, signed with the application's
+/// HMAC secret for `purpose`.
+///
+/// The purpose, e.g. `b"unsubscribe"`, is signed along with the ids, so a token
+/// issued for one kind of link is rejected by every other.
+pub fn sign(purpose: &[u8], ids: &[Uuid], secret: &SecretString) -> String {
+    let payload = URL_SAFE_NO_PAD.encode(concat(ids));
+    let tag = URL_SAFE_NO_PAD.encode(mac(purpose, ids, secret).finalize().into_bytes());
+    format!("{payload}.{tag}")
+}
+
+/// Returns the ids carried by `token` if it was signed for `purpose`.
+pub fn verify(purpose: &[u8], token: &str, secret: &SecretString) -> Result<Vec<Uuid>, String> {
+    let (payload, tag) = token
+        .split_once('.')
+        .ok_or_else(|| "token is malformed".to_owned())?;
+    let payload = URL_SAFE_NO_PAD
+        .decode(payload)
+        .map_err(|_| "token payload is not base64".to_owned())?;
+    let ids = payload
+        .chunks(16)
+        .map(Uuid::from_slice)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| "token payload is not a list of ids".to_owned())?;
+    let tag = URL_SAFE_NO_PAD
+        .decode(tag)
+        .map_err(|_| "token signature is not base64".to_owned())?;
+
+    mac(purpose, &ids, secret)
+        .verify_slice(&tag)
+        .map_err(|_| "token signature is invalid".to_owned())?;
+    Ok(ids)
+}
+
+fn concat(ids: &[Uuid]) -> Vec<u8> {
+    ids.iter().flat_map(|id| *id.as_bytes()).collect()
+}
+
+fn mac(purpose: &[u8], ids: &[Uuid], secret: &SecretString) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.expose_secret().as_bytes())
+        .expect("HMAC accepts keys of any length");
+    // Purposes never contain a NUL byte, so it marks where the ids start.
+    mac.update(purpose);
+    mac.update(b"\0");
+    mac.update(&concat(ids));
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sign, verify};
+    use claims::{assert_err, assert_ok_eq};
+    use secrecy::SecretString;
+    use uuid::Uuid;
+
+    fn secret() -> SecretString {
+        SecretString::from("long-and-very-secret-random-key")
+    }
+
+    #[test]
+    fn a_signed_token_verifies_to_its_ids() {
+        let ids = [Uuid::new_v4(), Uuid::new_v4()];
+        let token = sign(b"open", &ids, &secret());
+        assert_ok_eq!(verify(b"open", &token, &secret()), ids.to_vec());
+    }
+
+    #[test]
+    fn tokens_are_url_safe() {
+        let token = sign(b"open", &[Uuid::new_v4(), Uuid::new_v4()], &secret());
+        assert!(
+            token
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+        );
+    }
+
+    #[test]
+    fn a_token_signed_for_another_purpose_is_rejected() {
+        let token = sign(b"open", &[Uuid::new_v4()], &secret());
+        assert_err!(verify(b"unsubscribe", &token, &secret()));
+    }
+
+    #[test]
+    fn a_token_signed_with_another_secret_is_rejected() {
+        let token = sign(
+            b"open",
+            &[Uuid::new_v4()],
+            &SecretString::from("another-secret"),
+        );
+        assert_err!(verify(b"open", &token, &secret()));
+    }
+
+    #[test]
+    fn a_token_with_other_ids_is_rejected() {
+        let token = sign(b"open", &[Uuid::new_v4()], &secret());
+        let (_, tag) = token.split_once('.').unwrap_or_default();
+        let other = sign(b"open", &[Uuid::new_v4()], &secret());
+        let (payload, _) = other.split_once('.').unwrap_or_default();
+        let forged = format!("{payload}.{tag}");
+        assert_err!(verify(b"open", &forged, &secret()));
+    }
+
+    #[test]
+    fn a_malformed_token_is_rejected() {
+        assert_err!(verify(b"open", "not-a-token", &secret()));
+        assert_err!(verify(b"open", "c2hvcnQ.c2hvcnQ", &secret()));
+    }
+}
@@ -0,0 +1,105 @@
+/// Where a subscriber is in their lifecycle, stored as the Postgres
+/// `subscription_status` enum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "subscription_status", rename_all = "snake_case")]
+pub enum SubscriptionStatus {
+    PendingConfirmation,
+    Confirmed,
+    Unsubscribed,
+    Bounced,
+    Complained,
+}
+
+impl SubscriptionStatus {
+    const ALL: [Self; 5] = [
+        Self::PendingConfirmation,
+        Self::Confirmed,
+        Self::Unsubscribed,
+        Self::Bounced,
+        Self::Complained,
+    ];
+
+    /// Whether a subscriber in this status may be moved to `next`. Staying in
+    /// the same status is always allowed, so repeated requests are harmless.
+    /// Readers who unsubscribed may sign up again.
+    pub const fn can_transition_to(self, next: Self) -> bool {
+        matches!(
+            (self, next),
+            (Self::PendingConfirmation, _)
+                | (
+                    Self::Confirmed,
+                    Self::Confirmed | Self::Unsubscribed | Self::Bounced | Self::Complained
+                )
+                | (
+                    Self::Unsubscribed,
+                    Self::Unsubscribed | Self::PendingConfirmation
+                )
+                | (Self::Bounced, Self::Bounced)
+                | (Self::Complained, Self::Complained)
+        )
+    }
+
+    /// Every status a subscriber may be in before moving to `next`.
+    pub fn predecessors(next: Self) -> Vec<Self> {
+        Self::ALL
+            .into_iter()
+            .filter(|status| status.can_transition_to(next))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SubscriptionStatus;
+
+    #[test]
+    fn a_pending_subscriber_can_confirm() {
+        assert!(
+            SubscriptionStatus::PendingConfirmation
+                .can_transition_to(SubscriptionStatus::Confirmed)
+        );
+    }
+
+    #[test]
+    fn a_confirmed_subscriber_can_leave() {
+        for next in [
+            SubscriptionStatus::Unsubscribed,
+            SubscriptionStatus::Bounced,
+            SubscriptionStatus::Complained,
+        ] {
+            assert!(SubscriptionStatus::Confirmed.can_transition_to(next));
+        }
+    }
+
+    #[test]
+    fn a_confirmed_subscriber_cannot_go_back_to_pending() {
+        assert!(
+            !SubscriptionStatus::Confirmed
+                .can_transition_to(SubscriptionStatus::PendingConfirmation)
+        );
+    }
+
+    #[test]
+    fn an_unsubscribed_subscriber_cannot_be_confirmed_again() {
+        assert!(!SubscriptionStatus::Unsubscribed.can_transition_to(SubscriptionStatus::Confirmed));
+    }
+
+    #[test]
+    fn an_unsubscribed_subscriber_can_sign_up_again() {
+        assert!(
+            SubscriptionStatus::Unsubscribed
+                .can_transition_to(SubscriptionStatus::PendingConfirmation)
+        );
+    }
+
+    #[test]
+    fn only_pending_and_confirmed_subscribers_can_be_confirmed() {
+        assert_eq!(
+            SubscriptionStatus::predecessors(SubscriptionStatus::Confirmed),
+            vec![
+                SubscriptionStatus::PendingConfirmation,
+                SubscriptionStatus::Confirmed
+            ]
+        );
+    }
+}
@@ -0,0 +1,30 @@
+/// Why an address is on the suppression list, stored as the Postgres
+/// `suppression_reason` enum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "suppression_reason", rename_all = "snake_case")]
+pub enum SuppressionReason {
+    /// The provider reported the address as undeliverable.
+    Bounce,
+    /// The recipient marked one of our emails as spam.
+    Complaint,
+    Unsubscribe,
+    /// Added by an admin.
+    Manual,
+}
+
+impl SuppressionReason {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Bounce => "bounce",
+            Self::Complaint => "complaint",
+            Self::Unsubscribe => "unsubscribe",
+            Self::Manual => "manual",
+        }
+    }
+}
+
+impl std::fmt::Display for SuppressionReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
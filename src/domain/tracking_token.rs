@@ -0,0 +1,114 @@
+use secrecy::SecretString;
+use uuid::Uuid;
+
+use super::signed_token;
+
+/// Identifies one delivery, i.e. an issue sent to a subscriber, signed with
+/// the application's HMAC secret.
+///
+/// Click tracking tokens also carry the link followed. Either way, tracking
+/// URLs cannot be forged to record activity for someone else.
+#[derive(Debug)]
+pub struct TrackingToken(String);
+
+const OPEN: &[u8] = b"open";
+const CLICK: &[u8] = b"click";
+
+impl TrackingToken {
+    pub fn new(newsletter_issue_id: Uuid, subscriber_id: Uuid, secret: &SecretString) -> Self {
+        Self(signed_token::sign(
+            OPEN,
+            &[newsletter_issue_id, subscriber_id],
+            secret,
+        ))
+    }
+
+    pub fn for_link(
+        newsletter_issue_id: Uuid,
+        subscriber_id: Uuid,
+        link_id: Uuid,
+        secret: &SecretString,
+    ) -> Self {
+        Self(signed_token::sign(
+            CLICK,
+            &[newsletter_issue_id, subscriber_id, link_id],
+            secret,
+        ))
+    }
+
+    /// Returns the newsletter issue id and subscriber id carried by `token`
+    /// if its signature is valid.
+    pub fn verify(token: &str, secret: &SecretString) -> Result<(Uuid, Uuid), String> {
+        match signed_token::verify(OPEN, token, secret)?[..] {
+            [newsletter_issue_id, subscriber_id] => Ok((newsletter_issue_id, subscriber_id)),
+            _ => Err("tracking token does not carry a delivery".to_owned()),
+        }
+    }
+
+    /// Returns the newsletter issue id, subscriber id and link id carried by
+    /// `token` if its signature is valid.
+    pub fn verify_link(token: &str, secret: &SecretString) -> Result<(Uuid, Uuid, Uuid), String> {
+        match signed_token::verify(CLICK, token, secret)?[..] {
+            [newsletter_issue_id, subscriber_id, link_id] => {
+                Ok((newsletter_issue_id, subscriber_id, link_id))
+            }
+            _ => Err("tracking token does not carry a link".to_owned()),
+        }
+    }
+}
+
+impl AsRef<str> for TrackingToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TrackingToken;
+    use crate::domain::UnsubscribeToken;
+    use claims::{assert_err, assert_ok_eq};
+    use secrecy::SecretString;
+    use uuid::Uuid;
+
+    fn secret() -> SecretString {
+        SecretString::from("long-and-very-secret-random-key")
+    }
+
+    #[test]
+    fn a_signed_token_verifies_to_its_delivery() {
+        let (newsletter_issue_id, subscriber_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let token = TrackingToken::new(newsletter_issue_id, subscriber_id, &secret());
+        assert_ok_eq!(
+            TrackingToken::verify(token.as_ref(), &secret()),
+            (newsletter_issue_id, subscriber_id)
+        );
+    }
+
+    #[test]
+    fn a_signed_link_token_verifies_to_its_delivery_and_link() {
+        let ids = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let token = TrackingToken::for_link(ids.0, ids.1, ids.2, &secret());
+        assert_ok_eq!(TrackingToken::verify_link(token.as_ref(), &secret()), ids);
+    }
+
+    #[test]
+    fn open_and_link_tokens_are_not_interchangeable() {
+        let (newsletter_issue_id, subscriber_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let open = TrackingToken::new(newsletter_issue_id, subscriber_id, &secret());
+        let link = TrackingToken::for_link(
+            newsletter_issue_id,
+            subscriber_id,
+            Uuid::new_v4(),
+            &secret(),
+        );
+        assert_err!(TrackingToken::verify_link(open.as_ref(), &secret()));
+        assert_err!(TrackingToken::verify(link.as_ref(), &secret()));
+    }
+
+    #[test]
+    fn a_tracking_token_is_not_an_unsubscribe_token() {
+        let token = TrackingToken::new(Uuid::new_v4(), Uuid::new_v4(), &secret());
+        assert_err!(UnsubscribeToken::verify(token.as_ref(), &secret()));
+    }
+}
@@ -0,0 +1,61 @@
+use secrecy::SecretString;
+use uuid::Uuid;
+
+use super::signed_token;
+
+const PURPOSE: &[u8] = b"unsubscribe";
+
+/// A subscriber id signed with the application's HMAC secret, so unsubscribe
+/// links can be verified without storing a token per subscriber.
+#[derive(Debug)]
+pub struct UnsubscribeToken(String);
+
+impl UnsubscribeToken {
+    pub fn new(subscriber_id: Uuid, secret: &SecretString) -> Self {
+        Self(signed_token::sign(PURPOSE, &[subscriber_id], secret))
+    }
+
+    /// Returns the subscriber id carried by `token` if its signature is valid.
+    pub fn verify(token: &str, secret: &SecretString) -> Result<Uuid, String> {
+        match signed_token::verify(PURPOSE, token, secret)?[..] {
+            [subscriber_id] => Ok(subscriber_id),
+            _ => Err("unsubscribe token does not carry a subscriber id".to_owned()),
+        }
+    }
+}
+
+impl AsRef<str> for UnsubscribeToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UnsubscribeToken;
+    use crate::domain::TrackingToken;
+    use claims::{assert_err, assert_ok_eq};
+    use secrecy::SecretString;
+    use uuid::Uuid;
+
+    fn secret() -> SecretString {
+        SecretString::from("long-and-very-secret-random-key")
+    }
+
+    #[test]
+    fn a_signed_token_verifies_to_its_subscriber_id() {
+        let subscriber_id = Uuid::new_v4();
+        let token = UnsubscribeToken::new(subscriber_id, &secret());
+        assert_ok_eq!(
+            UnsubscribeToken::verify(token.as_ref(), &secret()),
+            subscriber_id
+        );
+    }
+
+    #[test]
+    fn an_unsubscribe_token_is_not_a_tracking_token() {
+        let token = UnsubscribeToken::new(Uuid::new_v4(), &secret());
+        assert_err!(TrackingToken::verify(token.as_ref(), &secret()));
+        assert_err!(TrackingToken::verify_link(token.as_ref(), &secret()));
+    }
+}
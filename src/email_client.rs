@@ -1,204 +0,0 @@
-use secrecy::{ExposeSecret, SecretString};
-use serde::Serialize;
-
-use crate::domain::SubscriberEmail;
-
-#[derive(Serialize)]
-#[serde(rename_all = "PascalCase")]
-struct SendEmailRequest<'a> {
-    from: &'a str,
-    to: &'a str,
-    subject: &'a str,
-    html_body: &'a str,
-    text_body: &'a str,
-}
-
-#[derive(Debug)]
-pub struct EmailClient {
-    http_client: reqwest::Client,
-    base_url: String,
-    sender: SubscriberEmail,
-    api_token: SecretString,
-}
-
-impl EmailClient {
-    pub fn new(
-        base_url: String,
-        sender: SubscriberEmail,
-        api_token: SecretString,
-        timeout: std::time::Duration,
-    ) -> Self {
-        Self {
-            http_client: reqwest::Client::builder()
-                .timeout(timeout)
-                .build()
-                .expect("failed to build email client"),
-            base_url,
-            sender,
-            api_token,
-        }
-    }
-
-    #[tracing::instrument(
-        name = "sending a confirmation email with postmark",
-        skip_all,
-        fields(sender = %self.sender.as_ref(), recipient)
-    )]
-    pub async fn send_email(
-        &self,
-        recipient: SubscriberEmail,
-        subject: &str,
-        html_content: &str,
-        text_content: &str,
-    ) -> Result<(), reqwest::Error> {
-        let url = format!("{}/email", self.base_url);
-        let request_body = SendEmailRequest {
-            from: self.sender.as_ref(),
-            to: recipient.as_ref(),
-            subject,
-            html_body: html_content,
-            text_body: text_content,
-        };
-        self.http_client
-            .post(&url)
-            .header("X-Postmark-Server-Token", self.api_token.expose_secret())
-            .json(&request_body)
-            .send()
-            .await?
-            .error_for_status()?;
-
-        Ok(())
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use anyhow::Result;
-    use claims::{assert_err, assert_ok};
-    use fake::faker::lorem::en::{Paragraph, Sentence};
-    use fake::{Fake, Faker, faker::internet::en::SafeEmail};
-    use reqwest::Method;
-    use reqwest::header::CONTENT_TYPE;
-    use secrecy::SecretString;
-    use wiremock::matchers::{any, header, header_exists, method, path};
-    use wiremock::{Mock, MockServer, ResponseTemplate};
-
-    use super::*;
-
-    struct SendEmailBodyMatcher;
-
-    impl wiremock::Match for SendEmailBodyMatcher {
-        fn matches(&self, request: &wiremock::Request) -> bool {
-            let result: Result<serde_json::Value, _> = serde_json::from_slice(&request.body);
-            result.is_ok_and(|body| {
-                body.get("From").is_some()
-                    && body.get("To").is_some()
-                    && body.get("Subject").is_some()
-                    && body.get("HtmlBody").is_some()
-                    && body.get("TextBody").is_some()
-            })
-        }
-    }
-
-    fn subject() -> String {
-        Sentence(1..2).fake()
-    }
-
-    fn content() -> String {
-        Paragraph(1..10).fake()
-    }
-
-    fn email() -> SubscriberEmail {
-        SubscriberEmail::parse(SafeEmail().fake()).expect("failed to parse email")
-    }
-
-    fn email_client(base_url: String) -> EmailClient {
-        EmailClient::new(
-            base_url,
-            email(),
-            SecretString::from(Faker.fake::<String>()),
-            std::time::Duration::from_millis(200),
-        )
-    }
-
-    #[tokio::test]
-    async fn send_email_sends_the_expected_request() -> Result<()> {
-        let mock_server = MockServer::start().await;
-        let email_client = email_client(mock_server.uri());
-
-        Mock::given(header_exists("X-Postmark-Server-Token"))
-            .and(header(CONTENT_TYPE, mime::APPLICATION_JSON.to_string()))
-            .and(path("/email"))
-            .and(method(Method::POST))
-            .and(SendEmailBodyMatcher)
-            .respond_with(ResponseTemplate::new(200))
-            .expect(1)
-            .mount(&mock_server)
-            .await;
-
-        let _ = email_client
-            .send_email(email(), &subject(), &content(), &content())
-            .await;
-
-        Ok(())
-    }
-
-    #[tokio::test]
-    async fn send_email_succeeds_if_the_server_returns_200() -> Result<()> {
-        let mock_server = MockServer::start().await;
-        let email_client = email_client(mock_server.uri());
-
-        Mock::given(header_exists("X-Postmark-Server-Token"))
-            .respond_with(ResponseTemplate::new(200))
-            .expect(1)
-            .mount(&mock_server)
-            .await;
-
-        let outcome = email_client
-            .send_email(email(), &subject(), &content(), &content())
-            .await;
-
-        assert_ok!(outcome);
-
-        Ok(())
-    }
-
-    #[tokio::test]
-    async fn send_email_fails_if_the_server_returns_500() {
-        let mock_server = MockServer::start().await;
-        let email_client = email_client(mock_server.uri());
-
-        Mock::given(any())
-            .respond_with(ResponseTemplate::new(500))
-            .expect(1)
-            .mount(&mock_server)
-            .await;
-
-        let outcome = email_client
-            .send_email(email(), &subject(), &content(), &content())
-            .await;
-
-        assert_err!(outcome);
-    }
-
-    #[tokio::test]
-    async fn send_email_times_out_if_the_server_takes_too_long() {
-        let mock_server = MockServer::start().await;
-        let email_client = email_client(mock_server.uri());
-
-        let response = ResponseTemplate::new(200)
-            // 3 minutes!
-            .set_delay(std::time::Duration::from_secs(180));
-        Mock::given(any())
-            .respond_with(response)
-            .expect(1)
-            .mount(&mock_server)
-            .await;
-
-        let outcome = email_client
-            .send_email(email(), &subject(), &content(), &content())
-            .await;
-
-        assert_err!(outcome);
-    }
-}
@@ -0,0 +1,158 @@
+use async_trait::async_trait;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+
+use super::{EmailError, EmailHeader, EmailSender, check_status, http_client};
+use crate::domain::SubscriberEmail;
+
+#[derive(Deserialize)]
+struct SendEmailResponse {
+    id: String,
+}
+
+/// Sends email through Mailgun's `/messages` HTTP API. `base_url` includes the
+/// sending domain, e.g. `https://api.mailgun.net/v3/mg.example.com`.
+#[derive(Debug)]
+pub struct MailgunClient {
+    http_client: reqwest::Client,
+    base_url: String,
+    sender: SubscriberEmail,
+    api_token: SecretString,
+}
+
+impl MailgunClient {
+    pub fn new(
+        base_url: String,
+        sender: SubscriberEmail,
+        api_token: SecretString,
+        timeout: std::time::Duration,
+    ) -> Self {
+        Self {
+            http_client: http_client(timeout),
+            base_url,
+            sender,
+            api_token,
+        }
+    }
+}
+
+#[async_trait]
+impl EmailSender for MailgunClient {
+    #[tracing::instrument(
+        name = "sending an email with mailgun",
+        skip_all,
+        fields(sender = %self.sender.as_ref())
+    )]
+    async fn send_email(
+        &self,
+        recipient: SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[EmailHeader],
+    ) -> Result<Option<String>, EmailError> {
+        let url = format!("{}/messages", self.base_url);
+        let mut form = vec![
+            ("from".to_owned(), self.sender.as_ref()),
+            ("to".to_owned(), recipient.as_ref()),
+            ("subject".to_owned(), subject),
+            ("text".to_owned(), text_content),
+            ("html".to_owned(), html_content),
+        ];
+        // Mailgun takes custom headers as `h:`-prefixed form fields.
+        form.extend(
+            headers
+                .iter()
+                .map(|h| (format!("h:{}", h.name), h.value.as_str())),
+        );
+        let response = self
+            .http_client
+            .post(&url)
+            .basic_auth("api", Some(self.api_token.expose_secret()))
+            .form(&form)
+            .send()
+            .await?;
+        let response = check_status(response)?;
+
+        // The email has been accepted at this point, an unexpected body only
+        // costs us the id.
+        Ok(response
+            .json::<SendEmailResponse>()
+            .await
+            .ok()
+            .map(|body| body.id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use claims::{assert_err, assert_ok};
+    use fake::{Fake, Faker, faker::internet::en::SafeEmail};
+    use secrecy::SecretString;
+    use wiremock::matchers::{any, body_string_contains, header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::parse(SafeEmail().fake()).expect("failed to parse email")
+    }
+
+    fn email_client(base_url: String) -> MailgunClient {
+        MailgunClient::new(
+            base_url,
+            email(),
+            SecretString::from(Faker.fake::<String>()),
+            std::time::Duration::from_millis(200),
+        )
+    }
+
+    #[tokio::test]
+    async fn send_email_sends_the_expected_request() -> Result<()> {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(header_exists("Authorization"))
+            .and(path("/messages"))
+            .and(method("POST"))
+            .and(body_string_contains("subject=Subject"))
+            .and(body_string_contains(
+                "h%3AList-Unsubscribe-Post=List-Unsubscribe%3DOne-Click",
+            ))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let headers = [EmailHeader::new(
+            "List-Unsubscribe-Post",
+            "List-Unsubscribe=One-Click",
+        )];
+        let outcome = email_client
+            .send_email(email(), "Subject", "<p>Body</p>", "Body", &headers)
+            .await;
+
+        assert_ok!(outcome);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(email(), "Subject", "<p>Body</p>", "Body", &[])
+            .await;
+
+        assert_err!(outcome);
+    }
+}
@@ -0,0 +1,211 @@
+mod mailgun;
+mod postmark;
+mod retry;
+mod sendgrid;
+mod sink;
+mod smtp;
+mod suppression;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use reqwest::header::RETRY_AFTER;
+use serde::Serialize;
+
+pub use mailgun::MailgunClient;
+pub use postmark::PostmarkClient;
+pub use retry::RetryPolicy;
+pub use sendgrid::SendGridClient;
+pub use sink::{FileSink, MemorySink, SentEmail};
+pub use smtp::SmtpClient;
+pub use suppression::{
+    SuppressingSender, lift_unsubscribe_suppression, suppress_address, suppress_subscriber,
+};
+
+use crate::domain::SubscriberEmail;
+
+/// The most emails a single [`EmailSender::send_batch`] call accepts, matching
+/// Postmark's `/email/batch` limit.
+pub const MAX_BATCH_SIZE: usize = 500;
+
+/// The email backend shared by the API and the workers, chosen at startup from
+/// `EmailClientSettings::provider`.
+pub type EmailClient = Arc<dyn EmailSender>;
+
+#[async_trait]
+pub trait EmailSender: std::fmt::Debug + Send + Sync {
+    /// Returns the id the provider assigned to the message, if it reports one,
+    /// so later events about the message can be matched to it.
+    async fn send_email(
+        &self,
+        recipient: SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[EmailHeader],
+    ) -> Result<Option<String>, EmailError>;
+
+    /// Sends up to [`MAX_BATCH_SIZE`] emails at once. The outer error means
+    /// nothing was sent; otherwise there is one result per email, in order, so
+    /// the caller can retry only the failures.
+    ///
+    /// Backends without a batch API send the emails one by one.
+    async fn send_batch(
+        &self,
+        emails: &[OutgoingEmail],
+    ) -> Result<Vec<Result<Option<String>, EmailError>>, EmailError> {
+        if emails.len() > MAX_BATCH_SIZE {
+            return Err(EmailError::BatchTooLarge(emails.len()));
+        }
+        let mut results = Vec::with_capacity(emails.len());
+        for email in emails {
+            let result = self
+                .send_email(
+                    email.recipient.clone(),
+                    &email.subject,
+                    &email.html_content,
+                    &email.text_content,
+                    &email.headers,
+                )
+                .await;
+            results.push(result);
+        }
+        Ok(results)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmailError {
+    #[error("failed to send email: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("email provider responded with {status}")]
+    Status {
+        status: StatusCode,
+        retry_after: Option<Duration>,
+    },
+    #[error("failed to write email: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to send email over smtp: {0}")]
+    Smtp(#[from] lettre::transport::smtp::Error),
+    #[error("failed to build email: {0}")]
+    Message(String),
+    #[error("timed out sending email")]
+    Timeout,
+    #[error("email was rejected with error code {code}: {message}")]
+    Rejected { code: i64, message: String },
+    #[error("a batch holds at most {MAX_BATCH_SIZE} emails, got {0}")]
+    BatchTooLarge(usize),
+    #[error("expected {expected} batch results, got {got}")]
+    BatchResults { expected: usize, got: usize },
+    #[error("the recipient is on the suppression list")]
+    Suppressed,
+    #[error("failed to check the suppression list: {0}")]
+    SuppressionCheck(#[from] sqlx::Error),
+}
+
+impl EmailError {
+    /// Whether sending again later may succeed: timeouts, connection failures,
+    /// rate limiting and server-side errors. Everything else, such as an
+    /// invalid recipient or a rejected request, will fail the same way again.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Request(e) => e.is_timeout() || e.is_connect(),
+            Self::Status { status, .. } => {
+                *status == StatusCode::TOO_MANY_REQUESTS
+                    || *status == StatusCode::REQUEST_TIMEOUT
+                    || status.is_server_error()
+            }
+            Self::Smtp(e) => {
+                e.is_transient()
+                    || e.is_timeout()
+                    || !(e.is_permanent() || e.is_client() || e.is_response() || e.is_tls())
+            }
+            // Nothing says which emails of the batch went out, so try them all again.
+            Self::Timeout | Self::SuppressionCheck(_) | Self::BatchResults { .. } => true,
+            Self::Io(_)
+            | Self::Message(_)
+            | Self::Rejected { .. }
+            | Self::BatchTooLarge(_)
+            | Self::Suppressed => false,
+        }
+    }
+
+    /// How long the provider asked us to wait before trying again, if it did.
+    pub const fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::Status { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+/// An extra header added to an outgoing email, such as `List-Unsubscribe`.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct EmailHeader {
+    pub name: String,
+    pub value: String,
+}
+
+impl EmailHeader {
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            value: value.into(),
+        }
+    }
+}
+
+/// One email of a batch sent with [`EmailSender::send_batch`].
+#[derive(Clone, Debug)]
+pub struct OutgoingEmail {
+    pub recipient: SubscriberEmail,
+    pub subject: String,
+    pub html_content: String,
+    pub text_content: String,
+    pub headers: Vec<EmailHeader>,
+}
+
+/// Fails the whole batch with [`EmailError::BatchResults`] unless `results`
+/// holds one result per email sent.
+pub fn check_batch_results(
+    results: Vec<Result<Option<String>, EmailError>>,
+    n_emails: usize,
+) -> Result<Vec<Result<Option<String>, EmailError>>, EmailError> {
+    if results.len() == n_emails {
+        Ok(results)
+    } else {
+        Err(EmailError::BatchResults {
+            expected: n_emails,
+            got: results.len(),
+        })
+    }
+}
+
+/// Turns an error status from an HTTP provider into [`EmailError::Status`],
+/// keeping `Retry-After` (in seconds) so retries can honour it.
+fn check_status(response: reqwest::Response) -> Result<reqwest::Response, EmailError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let retry_after = response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .map(Duration::from_secs);
+    Err(EmailError::Status {
+        status,
+        retry_after,
+    })
+}
+
+fn http_client(timeout: std::time::Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .expect("failed to build email client")
+}
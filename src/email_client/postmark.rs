@@ -0,0 +1,507 @@
+use async_trait::async_trait;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    EmailError, EmailHeader, EmailSender, MAX_BATCH_SIZE, OutgoingEmail, check_status, http_client,
+};
+use crate::domain::SubscriberEmail;
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendEmailRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    html_body: &'a str,
+    text_body: &'a str,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    headers: &'a [EmailHeader],
+}
+
+#[derive(Deserialize)]
+struct SendEmailResponse {
+    #[serde(rename = "MessageID")]
+    message_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct BatchResponseEntry {
+    error_code: i64,
+    message: String,
+    #[serde(rename = "MessageID", default)]
+    message_id: Option<String>,
+}
+
+/// Sends email through Postmark's `/email` and `/email/batch` HTTP APIs.
+#[derive(Debug)]
+pub struct PostmarkClient {
+    http_client: reqwest::Client,
+    base_url: String,
+    sender: SubscriberEmail,
+    api_token: SecretString,
+}
+
+impl PostmarkClient {
+    pub fn new(
+        base_url: String,
+        sender: SubscriberEmail,
+        api_token: SecretString,
+        timeout: std::time::Duration,
+    ) -> Self {
+        Self {
+            http_client: http_client(timeout),
+            base_url,
+            sender,
+            api_token,
+        }
+    }
+}
+
+#[async_trait]
+impl EmailSender for PostmarkClient {
+    #[tracing::instrument(
+        name = "sending an email with postmark",
+        skip_all,
+        fields(sender = %self.sender.as_ref(), recipient)
+    )]
+    async fn send_email(
+        &self,
+        recipient: SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[EmailHeader],
+    ) -> Result<Option<String>, EmailError> {
+        let url = format!("{}/email", self.base_url);
+        let request_body = SendEmailRequest {
+            from: self.sender.as_ref(),
+            to: recipient.as_ref(),
+            subject,
+            html_body: html_content,
+            text_body: text_content,
+            headers,
+        };
+        let response = self
+            .http_client
+            .post(&url)
+            .header("X-Postmark-Server-Token", self.api_token.expose_secret())
+            .json(&request_body)
+            .send()
+            .await?;
+        let response = check_status(response)?;
+
+        // The email has been accepted at this point, an unexpected body only
+        // costs us the id.
+        Ok(response
+            .json::<SendEmailResponse>()
+            .await
+            .ok()
+            .map(|body| body.message_id))
+    }
+
+    #[tracing::instrument(
+        name = "sending a batch of emails with postmark",
+        skip_all,
+        fields(sender = %self.sender.as_ref(), n_emails = emails.len())
+    )]
+    async fn send_batch(
+        &self,
+        emails: &[OutgoingEmail],
+    ) -> Result<Vec<Result<Option<String>, EmailError>>, EmailError> {
+        if emails.len() > MAX_BATCH_SIZE {
+            return Err(EmailError::BatchTooLarge(emails.len()));
+        }
+        if emails.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let url = format!("{}/email/batch", self.base_url);
+        let request_body: Vec<_> = emails
+            .iter()
+            .map(|email| SendEmailRequest {
+                from: self.sender.as_ref(),
+                to: email.recipient.as_ref(),
+                subject: &email.subject,
+                html_body: &email.html_content,
+                text_body: &email.text_content,
+                headers: &email.headers,
+            })
+            .collect();
+        let response = self
+            .http_client
+            .post(&url)
+            .header("X-Postmark-Server-Token", self.api_token.expose_secret())
+            .json(&request_body)
+            .send()
+            .await?;
+        let response = check_status(response)?;
+
+        // Postmark answers with one entry per message, in the order they were
+        // sent. The batch has been accepted at this point, so like `send_email`
+        // an unexpected body only costs us the ids and per-message errors.
+        let entries = match response.json::<Vec<BatchResponseEntry>>().await {
+            Ok(entries) if entries.len() == emails.len() => entries,
+            Ok(entries) => {
+                tracing::warn!(
+                    "expected {} batch results, got {}, assuming all were sent",
+                    emails.len(),
+                    entries.len()
+                );
+                return Ok(emails.iter().map(|_| Ok(None)).collect());
+            }
+            Err(e) => {
+                tracing::warn!("failed to read the batch results, assuming all were sent: {e}");
+                return Ok(emails.iter().map(|_| Ok(None)).collect());
+            }
+        };
+        Ok(entries
+            .into_iter()
+            .map(|entry| match entry.error_code {
+                0 => Ok(entry.message_id),
+                code => Err(EmailError::Rejected {
+                    code,
+                    message: entry.message,
+                }),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use claims::{assert_err, assert_ok};
+    use fake::faker::lorem::en::{Paragraph, Sentence};
+    use fake::{Fake, Faker, faker::internet::en::SafeEmail};
+    use reqwest::Method;
+    use reqwest::header::CONTENT_TYPE;
+    use secrecy::SecretString;
+    use wiremock::matchers::{any, body_partial_json, header, header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    struct SendEmailBodyMatcher;
+
+    impl wiremock::Match for SendEmailBodyMatcher {
+        fn matches(&self, request: &wiremock::Request) -> bool {
+            let result: Result<serde_json::Value, _> = serde_json::from_slice(&request.body);
+            result.is_ok_and(|body| {
+                body.get("From").is_some()
+                    && body.get("To").is_some()
+                    && body.get("Subject").is_some()
+                    && body.get("HtmlBody").is_some()
+                    && body.get("TextBody").is_some()
+            })
+        }
+    }
+
+    struct SendBatchBodyMatcher;
+
+    impl wiremock::Match for SendBatchBodyMatcher {
+        fn matches(&self, request: &wiremock::Request) -> bool {
+            let result: Result<Vec<serde_json::Value>, _> = serde_json::from_slice(&request.body);
+            result.is_ok_and(|messages| {
+                !messages.is_empty()
+                    && messages.iter().all(|body| {
+                        body.get("From").is_some()
+                            && body.get("To").is_some()
+                            && body.get("Subject").is_some()
+                            && body.get("HtmlBody").is_some()
+                            && body.get("TextBody").is_some()
+                    })
+            })
+        }
+    }
+
+    fn subject() -> String {
+        Sentence(1..2).fake()
+    }
+
+    fn content() -> String {
+        Paragraph(1..10).fake()
+    }
+
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::parse(SafeEmail().fake()).expect("failed to parse email")
+    }
+
+    fn outgoing_email() -> OutgoingEmail {
+        OutgoingEmail {
+            recipient: email(),
+            subject: subject(),
+            html_content: content(),
+            text_content: content(),
+            headers: Vec::new(),
+        }
+    }
+
+    fn email_client(base_url: String) -> PostmarkClient {
+        PostmarkClient::new(
+            base_url,
+            email(),
+            SecretString::from(Faker.fake::<String>()),
+            std::time::Duration::from_millis(200),
+        )
+    }
+
+    #[tokio::test]
+    async fn send_email_sends_the_expected_request() -> Result<()> {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(header_exists("X-Postmark-Server-Token"))
+            .and(header(CONTENT_TYPE, mime::APPLICATION_JSON.to_string()))
+            .and(path("/email"))
+            .and(method(Method::POST))
+            .and(SendEmailBodyMatcher)
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let _ = email_client
+            .send_email(email(), &subject(), &content(), &content(), &[])
+            .await;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn send_email_forwards_extra_headers() -> Result<()> {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(path("/email"))
+            .and(body_partial_json(serde_json::json!({
+                "Headers": [{"Name": "List-Unsubscribe-Post", "Value": "List-Unsubscribe=One-Click"}],
+            })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let headers = [EmailHeader::new(
+            "List-Unsubscribe-Post",
+            "List-Unsubscribe=One-Click",
+        )];
+        let outcome = email_client
+            .send_email(email(), &subject(), &content(), &content(), &headers)
+            .await;
+
+        assert_ok!(outcome);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn send_email_succeeds_if_the_server_returns_200() -> Result<()> {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(header_exists("X-Postmark-Server-Token"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(email(), &subject(), &content(), &content(), &[])
+            .await;
+
+        assert_ok!(outcome);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn send_email_returns_the_message_id() -> Result<()> {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(path("/email"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "ErrorCode": 0,
+                "Message": "OK",
+                "MessageID": "b7bc2f4a-e38e-4336-af7d-e6c392c2f817",
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(email(), &subject(), &content(), &content(), &[])
+            .await;
+
+        assert_eq!(
+            assert_ok!(outcome).as_deref(),
+            Some("b7bc2f4a-e38e-4336-af7d-e6c392c2f817")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(email(), &subject(), &content(), &content(), &[])
+            .await;
+
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_times_out_if_the_server_takes_too_long() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        let response = ResponseTemplate::new(200)
+            // 3 minutes!
+            .set_delay(std::time::Duration::from_secs(180));
+        Mock::given(any())
+            .respond_with(response)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(email(), &subject(), &content(), &content(), &[])
+            .await;
+
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_batch_sends_the_expected_request() -> Result<()> {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(header_exists("X-Postmark-Server-Token"))
+            .and(header(CONTENT_TYPE, mime::APPLICATION_JSON.to_string()))
+            .and(path("/email/batch"))
+            .and(method(Method::POST))
+            .and(SendBatchBodyMatcher)
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                {"ErrorCode": 0, "Message": "OK"},
+                {"ErrorCode": 0, "Message": "OK"},
+            ])))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_batch(&[outgoing_email(), outgoing_email()])
+            .await;
+
+        let results = assert_ok!(outcome);
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(Result::is_ok));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn send_batch_reports_per_recipient_failures() -> Result<()> {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(path("/email/batch"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                {"ErrorCode": 0, "Message": "OK", "MessageID": "first"},
+                {"ErrorCode": 406, "Message": "You tried to send to a recipient that has been marked as inactive."},
+                {"ErrorCode": 0, "Message": "OK", "MessageID": "third"},
+            ])))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_batch(&[outgoing_email(), outgoing_email(), outgoing_email()])
+            .await;
+
+        let results = assert_ok!(outcome);
+        assert_eq!(assert_ok!(&results[0]).as_deref(), Some("first"));
+        assert!(matches!(
+            results[1],
+            Err(EmailError::Rejected { code: 406, .. })
+        ));
+        assert_eq!(assert_ok!(&results[2]).as_deref(), Some("third"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn send_batch_assumes_an_accepted_batch_was_sent_if_the_body_is_unexpected() -> Result<()>
+    {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(path("/email/batch"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("not json"))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/email/batch"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                {"ErrorCode": 0, "Message": "OK", "MessageID": "first"},
+            ])))
+            .mount(&mock_server)
+            .await;
+
+        for _ in 0..2 {
+            let outcome = email_client
+                .send_batch(&[outgoing_email(), outgoing_email()])
+                .await;
+
+            let results = assert_ok!(outcome);
+            assert_eq!(results.len(), 2);
+            assert!(results.iter().all(|result| matches!(result, Ok(None))));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn send_batch_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_batch(&[outgoing_email()]).await;
+
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_batch_rejects_batches_over_the_limit() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let emails = vec![outgoing_email(); MAX_BATCH_SIZE + 1];
+        let outcome = email_client.send_batch(&emails).await;
+
+        assert!(matches!(outcome, Err(EmailError::BatchTooLarge(_))));
+    }
+}
@@ -0,0 +1,90 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+use super::EmailError;
+
+/// How often and how patiently the queue workers retry transient failures.
+/// They reschedule the email instead of waiting in-process, so nothing is held
+/// while it waits.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// How long to wait before retry number `n_retries`, counting from 0, or
+    /// `None` to give up on `error`.
+    pub fn delay(&self, n_retries: u32, error: &EmailError) -> Option<Duration> {
+        if n_retries >= self.max_retries || !error.is_transient() {
+            return None;
+        }
+        if let Some(retry_after) = error.retry_after() {
+            return Some(retry_after);
+        }
+        let backoff = self
+            .base_delay
+            .saturating_mul(2_u32.saturating_pow(n_retries))
+            .min(self.max_delay);
+        // Pick a random delay in the upper half so emails that failed together
+        // do not retry together.
+        let millis = u64::try_from(backoff.as_millis()).unwrap_or(u64::MAX);
+        Some(Duration::from_millis(
+            rand::rng().random_range(millis / 2..=millis),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_none, assert_some};
+    use reqwest::StatusCode;
+
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+        }
+    }
+
+    #[test]
+    fn retry_after_is_honoured_even_beyond_the_max_delay() {
+        let error = EmailError::Status {
+            status: StatusCode::TOO_MANY_REQUESTS,
+            retry_after: Some(Duration::from_mins(2)),
+        };
+
+        assert_eq!(policy().delay(0, &error), Some(Duration::from_mins(2)));
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_up_to_the_max_delay() {
+        let error = EmailError::Timeout;
+
+        for (n_retries, ceiling) in [(0, 100), (1, 200), (2, 400), (3, 800), (4, 1000), (9, 1000)] {
+            let delay = assert_some!(policy().delay(n_retries, &error));
+            assert!(delay >= Duration::from_millis(ceiling / 2));
+            assert!(delay <= Duration::from_millis(ceiling));
+        }
+    }
+
+    #[test]
+    fn retries_stop_after_max_retries() {
+        assert_none!(policy().delay(10, &EmailError::Timeout));
+    }
+
+    #[test]
+    fn permanent_failures_are_not_retried() {
+        let error = EmailError::Status {
+            status: StatusCode::UNPROCESSABLE_ENTITY,
+            retry_after: None,
+        };
+
+        assert_none!(policy().delay(0, &error));
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Serialize;
+
+use super::{EmailError, EmailHeader, EmailSender, check_status, http_client};
+use crate::domain::SubscriberEmail;
+
+#[derive(Serialize)]
+struct SendEmailRequest<'a> {
+    personalizations: [Personalization<'a>; 1],
+    from: Address<'a>,
+    subject: &'a str,
+    content: [Content<'a>; 2],
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    headers: HashMap<&'a str, &'a str>,
+}
+
+#[derive(Serialize)]
+struct Personalization<'a> {
+    to: [Address<'a>; 1],
+}
+
+#[derive(Serialize)]
+struct Address<'a> {
+    email: &'a str,
+}
+
+#[derive(Serialize)]
+struct Content<'a> {
+    #[serde(rename = "type")]
+    mime_type: &'a str,
+    value: &'a str,
+}
+
+/// Sends email through `SendGrid`'s `/v3/mail/send` HTTP API.
+#[derive(Debug)]
+pub struct SendGridClient {
+    http_client: reqwest::Client,
+    base_url: String,
+    sender: SubscriberEmail,
+    api_token: SecretString,
+}
+
+impl SendGridClient {
+    pub fn new(
+        base_url: String,
+        sender: SubscriberEmail,
+        api_token: SecretString,
+        timeout: std::time::Duration,
+    ) -> Self {
+        Self {
+            http_client: http_client(timeout),
+            base_url,
+            sender,
+            api_token,
+        }
+    }
+}
+
+#[async_trait]
+impl EmailSender for SendGridClient {
+    #[tracing::instrument(
+        name = "sending an email with sendgrid",
+        skip_all,
+        fields(sender = %self.sender.as_ref())
+    )]
+    async fn send_email(
+        &self,
+        recipient: SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[EmailHeader],
+    ) -> Result<Option<String>, EmailError> {
+        let url = format!("{}/v3/mail/send", self.base_url);
+        let request_body = SendEmailRequest {
+            personalizations: [Personalization {
+                to: [Address {
+                    email: recipient.as_ref(),
+                }],
+            }],
+            from: Address {
+                email: self.sender.as_ref(),
+            },
+            subject,
+            // SendGrid requires the plain text part to come first.
+            content: [
+                Content {
+                    mime_type: "text/plain",
+                    value: text_content,
+                },
+                Content {
+                    mime_type: "text/html",
+                    value: html_content,
+                },
+            ],
+            headers: headers
+                .iter()
+                .map(|h| (h.name.as_str(), h.value.as_str()))
+                .collect(),
+        };
+        let response = self
+            .http_client
+            .post(&url)
+            .bearer_auth(self.api_token.expose_secret())
+            .json(&request_body)
+            .send()
+            .await?;
+        let response = check_status(response)?;
+
+        // SendGrid answers with an empty body and reports the id in a header.
+        Ok(response
+            .headers()
+            .get("X-Message-Id")
+            .and_then(|value| value.to_str().ok())
+            .map(ToOwned::to_owned))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use claims::{assert_err, assert_ok};
+    use fake::{Fake, Faker, faker::internet::en::SafeEmail};
+    use secrecy::SecretString;
+    use wiremock::matchers::{any, body_partial_json, header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::parse(SafeEmail().fake()).expect("failed to parse email")
+    }
+
+    fn email_client(base_url: String) -> SendGridClient {
+        SendGridClient::new(
+            base_url,
+            email(),
+            SecretString::from(Faker.fake::<String>()),
+            std::time::Duration::from_millis(200),
+        )
+    }
+
+    #[tokio::test]
+    async fn send_email_sends_the_expected_request() -> Result<()> {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let recipient = email();
+
+        Mock::given(header_exists("Authorization"))
+            .and(path("/v3/mail/send"))
+            .and(method("POST"))
+            .and(body_partial_json(serde_json::json!({
+                "personalizations": [{"to": [{"email": recipient.as_ref()}]}],
+                "subject": "Subject",
+                "headers": {"List-Unsubscribe-Post": "List-Unsubscribe=One-Click"},
+            })))
+            .respond_with(ResponseTemplate::new(202))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let headers = [EmailHeader::new(
+            "List-Unsubscribe-Post",
+            "List-Unsubscribe=One-Click",
+        )];
+        let outcome = email_client
+            .send_email(recipient, "Subject", "<p>Body</p>", "Body", &headers)
+            .await;
+
+        assert_ok!(outcome);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(email(), "Subject", "<p>Body</p>", "Body", &[])
+            .await;
+
+        assert_err!(outcome);
+    }
+}
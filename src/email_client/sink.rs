@@ -0,0 +1,194 @@
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
+
+use async_trait::async_trait;
+use serde::Serialize;
+use uuid::Uuid;
+
+use super::{EmailError, EmailHeader, EmailSender};
+use crate::domain::SubscriberEmail;
+use crate::telemetry::spawn_blocking_with_tracing;
+
+/// An email captured by one of the local development sinks.
+#[derive(Clone, Debug, Serialize)]
+pub struct SentEmail {
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+    pub headers: Vec<EmailHeader>,
+}
+
+impl SentEmail {
+    fn new(
+        sender: &SubscriberEmail,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[EmailHeader],
+    ) -> Self {
+        Self {
+            from: sender.as_ref().to_owned(),
+            to: recipient.as_ref().to_owned(),
+            subject: subject.to_owned(),
+            html_body: html_content.to_owned(),
+            text_body: text_content.to_owned(),
+            headers: headers.to_vec(),
+        }
+    }
+}
+
+/// Keeps every email in memory instead of delivering it. Each email is also
+/// logged in full, which is how the configured `memory` provider is inspected
+/// since it is only reachable as an `EmailClient`.
+#[derive(Debug)]
+pub struct MemorySink {
+    sender: SubscriberEmail,
+    sent: Mutex<Vec<SentEmail>>,
+}
+
+impl MemorySink {
+    pub const fn new(sender: SubscriberEmail) -> Self {
+        Self {
+            sender,
+            sent: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn sent(&self) -> Vec<SentEmail> {
+        self.sent
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+#[async_trait]
+impl EmailSender for MemorySink {
+    #[tracing::instrument(name = "capturing an email in memory", skip_all)]
+    async fn send_email(
+        &self,
+        recipient: SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[EmailHeader],
+    ) -> Result<Option<String>, EmailError> {
+        let email = SentEmail::new(
+            &self.sender,
+            &recipient,
+            subject,
+            html_content,
+            text_content,
+            headers,
+        );
+        tracing::info!(
+            from = %email.from,
+            to = %email.to,
+            subject = %email.subject,
+            headers = ?email.headers,
+            html_body = %email.html_body,
+            text_body = %email.text_body,
+            "captured email"
+        );
+        self.sent
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(email);
+        Ok(None)
+    }
+}
+
+/// Writes every email as a JSON file to `directory` instead of delivering it.
+#[derive(Debug)]
+pub struct FileSink {
+    sender: SubscriberEmail,
+    directory: PathBuf,
+}
+
+impl FileSink {
+    pub const fn new(sender: SubscriberEmail, directory: PathBuf) -> Self {
+        Self { sender, directory }
+    }
+}
+
+#[async_trait]
+impl EmailSender for FileSink {
+    #[tracing::instrument(name = "writing an email to disk", skip_all)]
+    async fn send_email(
+        &self,
+        recipient: SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[EmailHeader],
+    ) -> Result<Option<String>, EmailError> {
+        let email = SentEmail::new(
+            &self.sender,
+            &recipient,
+            subject,
+            html_content,
+            text_content,
+            headers,
+        );
+        let contents = serde_json::to_vec_pretty(&email).map_err(std::io::Error::other)?;
+        let directory = self.directory.clone();
+        let path = directory.join(format!("{}.json", Uuid::new_v4()));
+
+        spawn_blocking_with_tracing(move || {
+            std::fs::create_dir_all(&directory)?;
+            std::fs::write(path, contents)
+        })
+        .await
+        .map_err(std::io::Error::other)??;
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::assert_ok;
+    use fake::{Fake, faker::internet::en::SafeEmail};
+
+    use super::*;
+
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::parse(SafeEmail().fake()).expect("failed to parse email")
+    }
+
+    #[tokio::test]
+    async fn the_memory_sink_keeps_sent_emails() {
+        let sink = MemorySink::new(email());
+        let recipient = email();
+        let recipient_address = recipient.as_ref().to_owned();
+
+        assert_ok!(
+            sink.send_email(recipient, "Subject", "<p>Body</p>", "Body", &[])
+                .await
+        );
+
+        let sent = sink.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to, recipient_address);
+        assert_eq!(sent[0].subject, "Subject");
+    }
+
+    #[tokio::test]
+    async fn the_file_sink_writes_one_file_per_email() {
+        let directory = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        let sink = FileSink::new(email(), directory.clone());
+
+        assert_ok!(
+            sink.send_email(email(), "Subject", "<p>Body</p>", "Body", &[])
+                .await
+        );
+
+        let files = std::fs::read_dir(&directory)
+            .expect("failed to read sink directory")
+            .count();
+        assert_eq!(files, 1);
+        std::fs::remove_dir_all(directory).expect("failed to clean up sink directory");
+    }
+}
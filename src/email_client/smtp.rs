@@ -0,0 +1,366 @@
+use async_trait::async_trait;
+use lettre::message::header::{HeaderName, HeaderValue};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::PoolConfig;
+use lettre::transport::smtp::authentication::{Credentials, Mechanism};
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use secrecy::ExposeSecret;
+
+use super::{EmailError, EmailHeader, EmailSender};
+use crate::configuration::{SmtpSettings, SmtpTls};
+use crate::domain::SubscriberEmail;
+
+/// Sends email through an SMTP relay, keeping a pool of open connections.
+#[derive(Debug)]
+pub struct SmtpClient {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    sender: SubscriberEmail,
+    timeout: std::time::Duration,
+}
+
+impl SmtpClient {
+    pub fn new(
+        settings: SmtpSettings,
+        sender: SubscriberEmail,
+        timeout: std::time::Duration,
+    ) -> Result<Self, EmailError> {
+        let mut builder = match settings.tls {
+            SmtpTls::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.host)
+            }
+            SmtpTls::Starttls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&settings.host)?
+            }
+            SmtpTls::Implicit => AsyncSmtpTransport::<Tokio1Executor>::relay(&settings.host)?,
+        }
+        .port(settings.port)
+        .timeout(Some(timeout))
+        .pool_config(PoolConfig::new().max_size(settings.pool_max_size));
+
+        if let (Some(username), Some(password)) = (settings.username, settings.password) {
+            builder = builder
+                .credentials(Credentials::new(
+                    username,
+                    password.expose_secret().to_owned(),
+                ))
+                .authentication(vec![Mechanism::Plain, Mechanism::Login]);
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            sender,
+            timeout,
+        })
+    }
+
+    fn message(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[EmailHeader],
+    ) -> Result<Message, EmailError> {
+        let from = mailbox(&self.sender)?;
+        let to = mailbox(recipient)?;
+        // Generate the Message-ID ourselves so it can be reported back.
+        let mut builder = Message::builder()
+            .from(from)
+            .to(to)
+            .subject(subject)
+            .message_id(None);
+        for header in headers {
+            let name = HeaderName::new_from_ascii(header.name.clone())
+                .map_err(|e| EmailError::Message(e.to_string()))?;
+            builder = builder.raw_header(HeaderValue::new(name, header.value.clone()));
+        }
+        builder
+            .multipart(MultiPart::alternative_plain_html(
+                text_content.to_owned(),
+                html_content.to_owned(),
+            ))
+            .map_err(|e| EmailError::Message(e.to_string()))
+    }
+}
+
+fn mailbox(email: &SubscriberEmail) -> Result<Mailbox, EmailError> {
+    email
+        .as_ref()
+        .parse()
+        .map_err(|e: lettre::address::AddressError| EmailError::Message(e.to_string()))
+}
+
+#[async_trait]
+impl EmailSender for SmtpClient {
+    #[tracing::instrument(
+        name = "sending an email over smtp",
+        skip_all,
+        fields(sender = %self.sender.as_ref())
+    )]
+    async fn send_email(
+        &self,
+        recipient: SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[EmailHeader],
+    ) -> Result<Option<String>, EmailError> {
+        let message = self.message(&recipient, subject, html_content, text_content, headers)?;
+        let message_id = message
+            .headers()
+            .get_raw("Message-ID")
+            .map(ToOwned::to_owned);
+        // lettre only bounds the TCP connect on tokio, so cap the whole exchange.
+        tokio::time::timeout(self.timeout, self.transport.send(message))
+            .await
+            .map_err(|_| EmailError::Timeout)??;
+
+        Ok(message_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex, PoisonError};
+
+    use claims::{assert_err, assert_ok, assert_some};
+    use fake::{Fake, faker::internet::en::SafeEmail};
+    use secrecy::SecretString;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// A minimal SMTP server that accepts every message, or rejects every
+    /// recipient when `reject_recipients` is set, and records what it was sent.
+    struct FakeSmtpServer {
+        port: u16,
+        commands: Arc<Mutex<Vec<String>>>,
+        data: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl FakeSmtpServer {
+        async fn start(reject_recipients: bool) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0")
+                .await
+                .expect("failed to bind fake smtp server");
+            let port = listener
+                .local_addr()
+                .expect("failed to read local address")
+                .port();
+            let commands = Arc::new(Mutex::new(Vec::new()));
+            let data = Arc::new(Mutex::new(Vec::new()));
+
+            let (commands_, data_) = (commands.clone(), data.clone());
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let (commands, data) = (commands_.clone(), data_.clone());
+                    tokio::spawn(async move {
+                        let (reader, mut writer) = stream.into_split();
+                        let mut lines = BufReader::new(reader).lines();
+                        writer.write_all(b"220 localhost ESMTP\r\n").await?;
+                        while let Some(line) = lines.next_line().await? {
+                            let verb = line
+                                .split_whitespace()
+                                .next()
+                                .unwrap_or_default()
+                                .to_uppercase();
+                            commands
+                                .lock()
+                                .unwrap_or_else(PoisonError::into_inner)
+                                .push(line);
+                            let reply: &[u8] = match verb.as_str() {
+                                "EHLO" => b"250-localhost\r\n250 AUTH PLAIN LOGIN\r\n",
+                                "AUTH" => b"235 2.7.0 Authentication successful\r\n",
+                                "RCPT" if reject_recipients => b"550 5.1.1 No such user\r\n",
+                                "DATA" => {
+                                    writer.write_all(b"354 Go ahead\r\n").await?;
+                                    let mut message = String::new();
+                                    while let Some(line) = lines.next_line().await? {
+                                        if line == "." {
+                                            break;
+                                        }
+                                        message.push_str(&line);
+                                        message.push('\n');
+                                    }
+                                    data.lock()
+                                        .unwrap_or_else(PoisonError::into_inner)
+                                        .push(message);
+                                    b"250 2.0.0 Queued\r\n"
+                                }
+                                "QUIT" => {
+                                    writer.write_all(b"221 Bye\r\n").await?;
+                                    break;
+                                }
+                                _ => b"250 OK\r\n",
+                            };
+                            writer.write_all(reply).await?;
+                        }
+                        Ok::<_, std::io::Error>(())
+                    });
+                }
+            });
+
+            Self {
+                port,
+                commands,
+                data,
+            }
+        }
+
+        fn commands(&self) -> Vec<String> {
+            self.commands
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone()
+        }
+
+        fn data(&self) -> Vec<String> {
+            self.data
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone()
+        }
+    }
+
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::parse(SafeEmail().fake()).expect("failed to parse email")
+    }
+
+    fn settings(port: u16) -> SmtpSettings {
+        SmtpSettings {
+            host: "127.0.0.1".into(),
+            port,
+            tls: SmtpTls::None,
+            username: Some("bulletin".into()),
+            password: Some(SecretString::from("password")),
+            pool_max_size: 2,
+        }
+    }
+
+    fn email_client(port: u16) -> SmtpClient {
+        SmtpClient::new(
+            settings(port),
+            email(),
+            std::time::Duration::from_millis(200),
+        )
+        .expect("failed to build smtp client")
+    }
+
+    #[tokio::test]
+    async fn send_email_authenticates_and_delivers_the_message() {
+        let server = FakeSmtpServer::start(false).await;
+        let email_client = email_client(server.port);
+        let recipient = email();
+        let recipient_address = recipient.as_ref().to_owned();
+
+        let headers = [EmailHeader::new(
+            "List-Unsubscribe-Post",
+            "List-Unsubscribe=One-Click",
+        )];
+        let outcome = email_client
+            .send_email(recipient, "Subject", "<p>Body</p>", "Body", &headers)
+            .await;
+
+        let message_id = assert_some!(assert_ok!(outcome));
+        let commands = server.commands();
+        assert!(commands.iter().any(|c| c.starts_with("AUTH PLAIN")));
+        assert!(
+            commands
+                .iter()
+                .any(|c| c.starts_with("RCPT TO:") && c.contains(&recipient_address))
+        );
+        let data = server.data();
+        assert_eq!(data.len(), 1);
+        assert!(data[0].contains("Subject: Subject"));
+        assert!(data[0].contains("List-Unsubscribe-Post: List-Unsubscribe=One-Click"));
+        assert!(data[0].contains("multipart/alternative"));
+        assert!(data[0].contains(&format!("Message-ID: {message_id}")));
+    }
+
+    #[tokio::test]
+    async fn send_email_reuses_pooled_connections() {
+        let server = FakeSmtpServer::start(false).await;
+        let email_client = email_client(server.port);
+
+        for _ in 0..3 {
+            assert_ok!(
+                email_client
+                    .send_email(email(), "Subject", "<p>Body</p>", "Body", &[])
+                    .await
+            );
+        }
+
+        let greetings = server
+            .commands()
+            .iter()
+            .filter(|c| c.starts_with("EHLO"))
+            .count();
+        // Connections are handed back to the pool by a spawned task, so the
+        // second send may still race it and open a new one.
+        assert!(greetings < 3);
+        assert_eq!(server.data().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn send_email_skips_authentication_without_credentials() {
+        let server = FakeSmtpServer::start(false).await;
+        let email_client = SmtpClient::new(
+            SmtpSettings {
+                username: None,
+                password: None,
+                ..settings(server.port)
+            },
+            email(),
+            std::time::Duration::from_millis(200),
+        )
+        .expect("failed to build smtp client");
+
+        assert_ok!(
+            email_client
+                .send_email(email(), "Subject", "<p>Body</p>", "Body", &[])
+                .await
+        );
+        assert!(!server.commands().iter().any(|c| c.starts_with("AUTH")));
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_rejects_the_recipient() {
+        let server = FakeSmtpServer::start(true).await;
+        let email_client = email_client(server.port);
+
+        let outcome = email_client
+            .send_email(email(), "Subject", "<p>Body</p>", "Body", &[])
+            .await;
+
+        assert_err!(outcome);
+        assert!(server.data().is_empty());
+    }
+
+    #[tokio::test]
+    async fn send_email_times_out_if_the_server_never_greets() {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind silent smtp server");
+        let port = listener
+            .local_addr()
+            .expect("failed to read local address")
+            .port();
+        // Accept connections but never say anything.
+        let _silent = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let _stream = stream;
+                    std::future::pending::<()>().await;
+                });
+            }
+        });
+        let email_client = email_client(port);
+
+        let outcome = email_client
+            .send_email(email(), "Subject", "<p>Body</p>", "Body", &[])
+            .await;
+
+        assert_err!(outcome);
+    }
+}
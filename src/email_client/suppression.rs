@@ -0,0 +1,189 @@
+use std::collections::HashSet;
+
+use async_trait::async_trait;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use super::{
+    EmailClient, EmailError, EmailHeader, EmailSender, MAX_BATCH_SIZE, OutgoingEmail,
+    check_batch_results,
+};
+use crate::domain::{SubscriberEmail, SuppressionReason};
+
+/// Wraps another backend and refuses to send to addresses on the suppression
+/// list, failing with [`EmailError::Suppressed`] instead.
+#[derive(Debug)]
+pub struct SuppressingSender {
+    inner: EmailClient,
+    pool: PgPool,
+}
+
+impl SuppressingSender {
+    pub const fn new(inner: EmailClient, pool: PgPool) -> Self {
+        Self { inner, pool }
+    }
+}
+
+#[async_trait]
+impl EmailSender for SuppressingSender {
+    async fn send_email(
+        &self,
+        recipient: SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[EmailHeader],
+    ) -> Result<Option<String>, EmailError> {
+        let suppressed = suppressed_addresses(&self.pool, &[recipient.as_ref()]).await?;
+        if !suppressed.is_empty() {
+            return Err(EmailError::Suppressed);
+        }
+        self.inner
+            .send_email(recipient, subject, html_content, text_content, headers)
+            .await
+    }
+
+    /// Suppressed emails fail on their own, the rest still go out as a batch.
+    async fn send_batch(
+        &self,
+        emails: &[OutgoingEmail],
+    ) -> Result<Vec<Result<Option<String>, EmailError>>, EmailError> {
+        if emails.len() > MAX_BATCH_SIZE {
+            return Err(EmailError::BatchTooLarge(emails.len()));
+        }
+        let recipients: Vec<&str> = emails
+            .iter()
+            .map(|email| email.recipient.as_ref())
+            .collect();
+        let suppressed = suppressed_addresses(&self.pool, &recipients).await?;
+        let is_suppressed =
+            |email: &OutgoingEmail| suppressed.contains(&email.recipient.as_ref().to_lowercase());
+
+        let allowed: Vec<OutgoingEmail> = emails
+            .iter()
+            .filter(|email| !is_suppressed(email))
+            .cloned()
+            .collect();
+        let sent = self.inner.send_batch(&allowed).await?;
+        // Checked up front, so every email that was sent has its result below.
+        let mut sent = check_batch_results(sent, allowed.len())?.into_iter();
+        let mut results = Vec::with_capacity(emails.len());
+        for email in emails {
+            if is_suppressed(email) {
+                results.push(Err(EmailError::Suppressed));
+            } else if let Some(result) = sent.next() {
+                results.push(result);
+            }
+        }
+        Ok(results)
+    }
+}
+
+/// The subset of `addresses` on the suppression list, lowercased.
+#[tracing::instrument(name = "check the suppression list", skip_all)]
+async fn suppressed_addresses(
+    pool: &PgPool,
+    addresses: &[&str],
+) -> Result<HashSet<String>, sqlx::Error> {
+    let addresses: Vec<String> = addresses
+        .iter()
+        .map(|address| address.to_lowercase())
+        .collect();
+    let rows = sqlx::query!(
+        r#"
+        SELECT address
+        FROM suppressions
+        WHERE address = ANY($1)
+        "#,
+        &addresses[..],
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("execute suppressed_addresses: {e:?}");
+        e
+    })?;
+    Ok(rows.into_iter().map(|row| row.address).collect())
+}
+
+/// Adds `address` to the suppression list. An address that is already on it
+/// keeps its original reason and source.
+#[tracing::instrument(name = "suppress an address", skip_all, fields(reason = %reason))]
+pub async fn suppress_address(
+    executor: impl PgExecutor<'_>,
+    address: &str,
+    reason: SuppressionReason,
+    source: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO suppressions (address, reason, source)
+        VALUES (LOWER($1), $2, $3)
+        ON CONFLICT (address) DO NOTHING
+        "#,
+        address,
+        reason as SuppressionReason,
+        source,
+    )
+    .execute(executor)
+    .await
+    .map_err(|e| {
+        tracing::error!("execute suppress_address: {e:?}");
+        e
+    })?;
+    Ok(())
+}
+
+/// Takes `address` off the suppression list if it is only there because its
+/// subscriber unsubscribed. Bounces, complaints and manual suppressions stay.
+#[tracing::instrument(name = "lift an unsubscribe suppression", skip_all)]
+pub async fn lift_unsubscribe_suppression(
+    executor: impl PgExecutor<'_>,
+    address: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM suppressions
+        WHERE address = LOWER($1) AND reason = $2
+        "#,
+        address,
+        SuppressionReason::Unsubscribe as SuppressionReason,
+    )
+    .execute(executor)
+    .await
+    .map_err(|e| {
+        tracing::error!("execute lift_unsubscribe_suppression: {e:?}");
+        e
+    })?;
+    Ok(())
+}
+
+/// Adds the address of the subscriber with `subscriber_id`, if any, to the
+/// suppression list.
+#[tracing::instrument(name = "suppress a subscriber", skip_all, fields(reason = %reason))]
+pub async fn suppress_subscriber(
+    executor: impl PgExecutor<'_>,
+    subscriber_id: Uuid,
+    reason: SuppressionReason,
+    source: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO suppressions (address, reason, source)
+        SELECT LOWER(email), $2, $3
+        FROM subscriptions
+        WHERE id = $1
+        ON CONFLICT (address) DO NOTHING
+        "#,
+        subscriber_id,
+        reason as SuppressionReason,
+        source,
+    )
+    .execute(executor)
+    .await
+    .map_err(|e| {
+        tracing::error!("execute suppress_subscriber: {e:?}");
+        e
+    })?;
+    Ok(())
+}
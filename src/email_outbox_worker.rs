@@ -0,0 +1,180 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::EmailClient;
+use crate::configuration::Settings;
+use crate::domain::SubscriberEmail;
+use crate::email_client::{EmailError, RetryPolicy, SuppressingSender};
+use crate::issue_delivery_worker::ExecutionOutcome;
+use crate::startup::get_connection_pool;
+
+struct OutboxEmail {
+    id: Uuid,
+    recipient: String,
+    subject: String,
+    html_content: String,
+    text_content: String,
+    n_retries: i16,
+}
+
+/// Writes an email to the outbox as part of `transaction`, so it is sent if and
+/// only if the transaction commits.
+#[tracing::instrument(name = "enqueue an outbox email", skip_all)]
+pub async fn enqueue_email(
+    transaction: &mut Transaction<'_, Postgres>,
+    recipient: &SubscriberEmail,
+    subject: &str,
+    html_content: &str,
+    text_content: &str,
+) -> Result<(), sqlx::Error> {
+    let query = sqlx::query!(
+        r#"
+        INSERT INTO email_outbox (id, recipient, subject, html_content, text_content)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        Uuid::new_v4(),
+        recipient.as_ref(),
+        subject,
+        html_content,
+        text_content,
+    );
+    transaction.execute(query).await.map_err(|e| {
+        tracing::error!("execute enqueue_email: {e:?}");
+        e
+    })?;
+    Ok(())
+}
+
+pub async fn run_dispatcher_until_stopped(configuration: Settings) -> Result<(), std::io::Error> {
+    let db_pool = get_connection_pool(&configuration.database);
+    let retry_policy = configuration.email_client.retry_policy();
+    let email_client: EmailClient = Arc::new(SuppressingSender::new(
+        configuration.email_client.client(),
+        db_pool.clone(),
+    ));
+    loop {
+        let outcome = try_dispatch_email(&db_pool, &email_client, &retry_policy).await;
+        // Confirmation emails should go out promptly, so poll more often than
+        // the issue delivery worker does.
+        if !matches!(outcome, Ok(ExecutionOutcome::TaskCompleted)) {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+}
+
+#[tracing::instrument(
+    name = "dispatch an outbox email",
+    skip_all,
+    fields(outbox_email_id = tracing::field::Empty),
+    err
+)]
+pub async fn try_dispatch_email(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    retry_policy: &RetryPolicy,
+) -> Result<ExecutionOutcome, sqlx::Error> {
+    let Some((transaction, email)) = dequeue_email(pool).await? else {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    };
+    tracing::Span::current().record("outbox_email_id", tracing::field::display(email.id));
+
+    match SubscriberEmail::parse(email.recipient.clone()) {
+        Ok(recipient) => {
+            if let Err(e) = email_client
+                .send_email(
+                    recipient,
+                    &email.subject,
+                    &email.html_content,
+                    &email.text_content,
+                    &[],
+                )
+                .await
+            {
+                if let Some(delay) = retry_policy.delay(email.n_retries.unsigned_abs().into(), &e) {
+                    tracing::warn!(
+                        "failed to dispatch an outbox email, retrying in {delay:?}: {e:?}"
+                    );
+                    reschedule_email(transaction, email.id, delay).await?;
+                    return Ok(ExecutionOutcome::TaskCompleted);
+                }
+                if matches!(e, EmailError::Suppressed) {
+                    tracing::info!("not sending an outbox email to a suppressed address");
+                } else {
+                    tracing::error!(
+                        "failed to dispatch an outbox email after {} retries, giving up: {e:?}",
+                        email.n_retries
+                    );
+                }
+            }
+        }
+        Err(e) => {
+            tracing::error!("skipping an outbox email with an invalid recipient: {e}");
+        }
+    }
+
+    delete_email(transaction, email.id).await?;
+
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+type PgTransaction = Transaction<'static, Postgres>;
+
+#[tracing::instrument(name = "dequeue an outbox email", skip_all)]
+async fn dequeue_email(pool: &PgPool) -> Result<Option<(PgTransaction, OutboxEmail)>, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    let email = sqlx::query_as!(
+        OutboxEmail,
+        r#"
+        SELECT id, recipient, subject, html_content, text_content, n_retries
+        FROM email_outbox
+        WHERE execute_after <= NOW()
+        ORDER BY created_at
+        FOR UPDATE
+        SKIP LOCKED
+        LIMIT 1
+        "#,
+    )
+    .fetch_optional(&mut *transaction)
+    .await?;
+
+    Ok(email.map(|email| (transaction, email)))
+}
+
+#[tracing::instrument(name = "delete an outbox email", skip_all)]
+async fn delete_email(mut transaction: PgTransaction, id: Uuid) -> Result<(), sqlx::Error> {
+    let query = sqlx::query!(
+        r#"
+        DELETE FROM email_outbox
+        WHERE id = $1
+        "#,
+        id,
+    );
+    transaction.execute(query).await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "reschedule an outbox email", skip_all)]
+async fn reschedule_email(
+    mut transaction: PgTransaction,
+    id: Uuid,
+    delay: Duration,
+) -> Result<(), sqlx::Error> {
+    let query = sqlx::query!(
+        r#"
+        UPDATE email_outbox
+        SET
+            n_retries = n_retries + 1,
+            execute_after = NOW() + MAKE_INTERVAL(secs => $2)
+        WHERE id = $1
+        "#,
+        id,
+        delay.as_secs_f64(),
+    );
+    transaction.execute(query).await?;
+    transaction.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,240 @@
+use std::path::Path;
+
+use minijinja::{Environment, UndefinedBehavior, Value, context};
+
+/// The templates every deployment has to provide, each as a `.html` and a
+/// `.txt` file.
+const REQUIRED_TEMPLATES: [&str; 2] = ["confirmation", "newsletter_issue"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum TemplateError {
+    #[error("failed to read templates: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to compile or render template: {0}")]
+    Template(#[from] minijinja::Error),
+}
+
+/// The HTML and plain text bodies of an email.
+#[derive(Debug)]
+pub struct RenderedEmail {
+    pub html: String,
+    pub text: String,
+}
+
+impl RenderedEmail {
+    /// Adds an invisible image loading `pixel_url` to the end of the HTML
+    /// body, whatever template it was rendered from.
+    pub fn add_open_pixel(&mut self, pixel_url: &str) {
+        let pixel = format!(
+            r#"<img src="{}" width="1" height="1" alt="" style="display:block;border:0;">"#,
+            htmlescape::encode_minimal(pixel_url)
+        );
+        let end = self.html.rfind("</body>").unwrap_or(self.html.len());
+        self.html.insert_str(end, &pixel);
+    }
+}
+
+/// Email bodies rendered from the templates under
+/// `ApplicationSettings::templates_directory`, compiled once at startup.
+///
+/// Templates are named by their path relative to that directory and can use
+/// `{% extends %}` and `{% include %}`. `.html` templates are auto-escaped,
+/// `.txt` templates are not, and using an undefined variable is an error.
+#[derive(Debug)]
+pub struct EmailTemplates {
+    env: Environment<'static>,
+}
+
+impl EmailTemplates {
+    pub fn load(directory: &Path) -> Result<Self, TemplateError> {
+        let mut env = Environment::new();
+        env.set_undefined_behavior(UndefinedBehavior::Strict);
+        env.set_trim_blocks(true);
+        env.set_lstrip_blocks(true);
+        add_templates(&mut env, directory, directory)?;
+
+        for name in REQUIRED_TEMPLATES {
+            env.get_template(&format!("{name}.html"))?;
+            env.get_template(&format!("{name}.txt"))?;
+        }
+        Ok(Self { env })
+    }
+
+    pub fn confirmation_email(
+        &self,
+        subject: &str,
+        name: &str,
+        confirmation_link: &str,
+    ) -> Result<RenderedEmail, TemplateError> {
+        self.render(
+            "confirmation",
+            &context! {
+                subject,
+                name,
+                // Built by us, escaping would only mangle the URL.
+                confirmation_link => Value::from_safe_string(confirmation_link.to_owned()),
+            },
+        )
+    }
+
+    /// Wraps an issue for one subscriber. The issue content is inserted as is,
+    /// it was authored by an admin or rendered from their Markdown.
+    pub fn newsletter_issue(
+        &self,
+        title: &str,
+        name: &str,
+        html_content: &str,
+        text_content: &str,
+        unsubscribe_link: &str,
+    ) -> Result<RenderedEmail, TemplateError> {
+        let unsubscribe_link = Value::from_safe_string(unsubscribe_link.to_owned());
+        let html = self
+            .env
+            .get_template("newsletter_issue.html")?
+            .render(context! {
+                subject => title,
+                name,
+                content => Value::from_safe_string(html_content.to_owned()),
+                unsubscribe_link,
+            })?;
+        let text = self
+            .env
+            .get_template("newsletter_issue.txt")?
+            .render(context! {
+                subject => title,
+                name,
+                content => text_content,
+                unsubscribe_link,
+            })?;
+        Ok(RenderedEmail { html, text })
+    }
+
+    fn render(&self, name: &str, context: &Value) -> Result<RenderedEmail, TemplateError> {
+        Ok(RenderedEmail {
+            html: self
+                .env
+                .get_template(&format!("{name}.html"))?
+                .render(context)?,
+            text: self
+                .env
+                .get_template(&format!("{name}.txt"))?
+                .render(context)?,
+        })
+    }
+}
+
+fn add_templates(
+    env: &mut Environment<'static>,
+    root: &Path,
+    directory: &Path,
+) -> Result<(), TemplateError> {
+    for entry in std::fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            add_templates(env, root, &path)?;
+            continue;
+        }
+        let name = path
+            .strip_prefix(root)
+            .expect("template path is under the templates directory")
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let source = std::fs::read_to_string(&path)?;
+        env.add_template_owned(name, source)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn templates() -> EmailTemplates {
+        EmailTemplates::load(Path::new("templates/email")).expect("failed to load templates")
+    }
+
+    #[test]
+    fn the_bundled_templates_compile() {
+        assert_ok!(EmailTemplates::load(Path::new("templates/email")));
+    }
+
+    #[test]
+    fn loading_fails_if_a_required_template_is_missing() {
+        let directory = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        std::fs::create_dir_all(&directory).expect("failed to create templates directory");
+        std::fs::write(directory.join("confirmation.html"), "{{ name }}")
+            .expect("failed to write template");
+
+        assert_err!(EmailTemplates::load(&directory));
+        std::fs::remove_dir_all(directory).expect("failed to clean up templates directory");
+    }
+
+    #[test]
+    fn confirmation_emails_contain_the_name_and_link() {
+        let link = "http://127.0.0.1/subscriptions/confirm?subscription_token=abc";
+
+        let email = assert_ok!(templates().confirmation_email("Welcome!", "Ursula", link));
+
+        assert!(email.html.contains("Ursula"));
+        assert!(email.html.contains(&format!("href=\"{link}\"")));
+        assert!(email.text.contains("Ursula"));
+        assert!(email.text.contains(link));
+    }
+
+    #[test]
+    fn names_are_escaped_in_html_but_not_in_text() {
+        let email = assert_ok!(templates().confirmation_email(
+            "Welcome!",
+            "Tom & Jerry",
+            "http://127.0.0.1"
+        ));
+
+        assert!(email.html.contains("Tom &amp; Jerry"));
+        assert!(email.text.contains("Tom & Jerry"));
+    }
+
+    #[test]
+    fn newsletter_issues_keep_their_html_and_get_an_unsubscribe_link() {
+        let link = "http://127.0.0.1/subscriptions/unsubscribe?token=abc";
+
+        let email = assert_ok!(templates().newsletter_issue(
+            "Issue #1",
+            "Ursula",
+            "<h1>News</h1>",
+            "News",
+            link,
+        ));
+
+        assert!(email.html.contains("<h1>News</h1>"));
+        assert!(email.html.contains("<title>Issue #1</title>"));
+        assert!(email.html.contains(&format!("href=\"{link}\"")));
+        assert!(email.text.contains("News"));
+        assert!(email.text.contains(link));
+    }
+
+    #[test]
+    fn open_pixels_go_inside_the_body_and_stay_out_of_the_text() {
+        let mut email = assert_ok!(templates().newsletter_issue(
+            "Issue #1",
+            "Ursula",
+            "<h1>News</h1>",
+            "News",
+            "http://127.0.0.1/subscriptions/unsubscribe?token=abc",
+        ));
+        let text = email.text.clone();
+
+        email.add_open_pixel("http://127.0.0.1/t/o/abc.gif");
+
+        let pixel = email
+            .html
+            .find(r#"<img src="http://127.0.0.1/t/o/abc.gif""#)
+            .expect("the pixel is missing");
+        assert!(pixel < email.html.rfind("</body>").expect("the body is not closed"));
+        assert_eq!(email.text, text);
+    }
+}
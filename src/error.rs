@@ -1,5 +1,8 @@
 use axum::response::{IntoResponse, Response};
-use axum::{Json, http::StatusCode};
+use axum::{
+    Json,
+    http::{StatusCode, header},
+};
 use serde_json::json;
 
 pub type Result<T, E = Report> = color_eyre::Result<T, E>;
@@ -52,10 +55,17 @@ pub enum HttpError {
     DatabaseError(#[from] sqlx::Error),
     #[error("authorization error: {0}")]
     AuthorizationError(String),
+    /// Like `AuthorizationError`, but asks the client for Basic credentials.
+    #[error("authorization error: {0}")]
+    BasicAuthError(String),
     #[error("not found")]
     NotFound,
     #[error("confilct: {0}")]
     Conflict(String),
+    #[error("gone: {0}")]
+    Gone(String),
+    #[error("unprocessable entity: {0}")]
+    UnprocessableEntity(String),
     #[error("unexpected error")]
     UnexpectedError,
 }
@@ -67,9 +77,15 @@ impl HttpError {
             Self::DatabaseError(_) | Self::UnexpectedError => {
                 (StatusCode::INTERNAL_SERVER_ERROR, "SERVICE_ERROR")
             }
-            Self::AuthorizationError(_) => (StatusCode::UNAUTHORIZED, "AUTHORIZATION_ERROR"),
+            Self::AuthorizationError(_) | Self::BasicAuthError(_) => {
+                (StatusCode::UNAUTHORIZED, "AUTHORIZATION_ERROR")
+            }
             Self::NotFound => (StatusCode::NOT_FOUND, "NOT_FOUND"),
             Self::Conflict(_) => (StatusCode::CONFLICT, "CONFLICT"),
+            Self::Gone(_) => (StatusCode::GONE, "GONE"),
+            Self::UnprocessableEntity(_) => {
+                (StatusCode::UNPROCESSABLE_ENTITY, "UNPROCESSABLE_ENTITY")
+            }
         };
 
         let client_body_error = json!({
@@ -78,6 +94,15 @@ impl HttpError {
             }
         });
 
+        if let Self::BasicAuthError(_) = self {
+            return (
+                status,
+                [(header::WWW_AUTHENTICATE, r#"Basic realm="publish""#)],
+                Json(client_body_error),
+            )
+                .into_response();
+        }
+
         (status, Json(client_body_error)).into_response()
     }
 }
@@ -0,0 +1,76 @@
+use axum::http::HeaderMap;
+
+const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+const MAX_KEY_LENGTH: usize = 50;
+
+#[derive(Debug)]
+pub struct IdempotencyKey(String);
+
+impl IdempotencyKey {
+    pub fn parse(s: String) -> Result<Self, String> {
+        if s.is_empty() {
+            return Err("the idempotency key cannot be empty".into());
+        }
+        if s.len() >= MAX_KEY_LENGTH {
+            return Err(format!(
+                "the idempotency key must be shorter than {MAX_KEY_LENGTH} characters"
+            ));
+        }
+        Ok(Self(s))
+    }
+
+    pub fn from_headers(headers: &HeaderMap) -> Result<Option<Self>, String> {
+        headers
+            .get(IDEMPOTENCY_KEY_HEADER)
+            .map(|value| {
+                let value = value
+                    .to_str()
+                    .map_err(|_| "the idempotency key is not valid UTF8".to_string())?;
+                Self::parse(value.to_owned())
+            })
+            .transpose()
+    }
+}
+
+impl AsRef<str> for IdempotencyKey {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+    use claims::{assert_err, assert_none, assert_ok, assert_some};
+
+    use super::*;
+
+    #[test]
+    fn empty_string_is_rejected() {
+        assert_err!(IdempotencyKey::parse(String::new()));
+    }
+
+    #[test]
+    fn a_50_character_key_is_rejected() {
+        assert_err!(IdempotencyKey::parse("a".repeat(50)));
+    }
+
+    #[test]
+    fn a_uuid_is_a_valid_key() {
+        assert_ok!(IdempotencyKey::parse(uuid::Uuid::new_v4().to_string()));
+    }
+
+    #[test]
+    fn a_missing_header_is_not_an_error() {
+        let key = IdempotencyKey::from_headers(&HeaderMap::new()).expect("failed to read headers");
+        assert_none!(key);
+    }
+
+    #[test]
+    fn the_key_is_read_from_the_header() {
+        let mut headers = HeaderMap::new();
+        headers.insert(IDEMPOTENCY_KEY_HEADER, HeaderValue::from_static("key"));
+        let key = IdempotencyKey::from_headers(&headers).expect("failed to read headers");
+        assert_some!(key);
+    }
+}
@@ -0,0 +1,8 @@
+mod key;
+mod persistence;
+
+pub use key::IdempotencyKey;
+pub use persistence::{NextAction, save_response, try_processing};
+
+/// Owner recorded for idempotency keys sent with unauthenticated requests.
+pub const ANONYMOUS_USER: &str = "anonymous";
@@ -0,0 +1,188 @@
+use axum::body::{Body, to_bytes};
+use axum::http::{HeaderName, HeaderValue, StatusCode};
+use axum::response::Response;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+
+use super::IdempotencyKey;
+use crate::error::{HttpError, Result};
+
+#[derive(Debug, sqlx::Type)]
+#[sqlx(type_name = "header_pair")]
+struct HeaderPairRecord {
+    name: String,
+    value: Vec<u8>,
+}
+
+pub enum NextAction {
+    StartProcessing(Transaction<'static, Postgres>),
+    ReturnSavedResponse(Response),
+}
+
+/// Claims `idempotency_key` for `username`, or returns the response saved by
+/// an earlier request with the same key. A concurrent request holding the key
+/// blocks this call until it commits or rolls back.
+///
+/// A key can only be replayed for the request it was first sent with, reusing
+/// it for a different `request` is rejected. Requests without a key are always
+/// processed.
+#[tracing::instrument(name = "try processing an idempotent request", skip_all)]
+pub async fn try_processing(
+    pool: &PgPool,
+    idempotency_key: Option<&IdempotencyKey>,
+    username: &str,
+    request: &(impl Serialize + Sync),
+) -> Result<NextAction> {
+    let mut transaction = pool.begin().await.map_err(HttpError::DatabaseError)?;
+    let Some(idempotency_key) = idempotency_key else {
+        return Ok(NextAction::StartProcessing(transaction));
+    };
+    let request_hash = request_hash(request)?;
+
+    let query = sqlx::query!(
+        r#"
+        INSERT INTO idempotency (username, idempotency_key, request_hash, created_at)
+        VALUES ($1, $2, $3, NOW())
+        ON CONFLICT DO NOTHING
+        "#,
+        username,
+        idempotency_key.as_ref(),
+        &request_hash,
+    );
+    let n_inserted_rows = transaction
+        .execute(query)
+        .await
+        .map_err(HttpError::DatabaseError)?
+        .rows_affected();
+
+    if n_inserted_rows > 0 {
+        return Ok(NextAction::StartProcessing(transaction));
+    }
+
+    let saved_response = get_saved_response(pool, idempotency_key, username, &request_hash)
+        .await?
+        .ok_or_else(|| {
+            tracing::error!("expected a saved response, but none was found");
+            HttpError::UnexpectedError
+        })?;
+    Ok(NextAction::ReturnSavedResponse(saved_response))
+}
+
+fn request_hash(request: &impl Serialize) -> Result<Vec<u8>, HttpError> {
+    let request = serde_json::to_vec(request).map_err(|e| {
+        tracing::error!("failed to serialise the request: {e:?}");
+        HttpError::UnexpectedError
+    })?;
+    Ok(Sha256::digest(request).to_vec())
+}
+
+#[tracing::instrument(name = "get a saved response", skip_all)]
+async fn get_saved_response(
+    pool: &PgPool,
+    idempotency_key: &IdempotencyKey,
+    username: &str,
+    request_hash: &[u8],
+) -> Result<Option<Response>> {
+    let saved_response = sqlx::query!(
+        r#"
+        SELECT
+            request_hash,
+            response_status_code AS "response_status_code!",
+            response_headers AS "response_headers!: Vec<HeaderPairRecord>",
+            response_body AS "response_body!"
+        FROM idempotency
+        WHERE username = $1 AND idempotency_key = $2
+        "#,
+        username,
+        idempotency_key.as_ref()
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(HttpError::DatabaseError)?;
+
+    let Some(r) = saved_response else {
+        return Ok(None);
+    };
+    if r.request_hash.is_some_and(|saved| saved != request_hash) {
+        return Err(HttpError::UnprocessableEntity(
+            "the idempotency key was already used for a different request".into(),
+        ))?;
+    }
+
+    let status_code = u16::try_from(r.response_status_code)
+        .ok()
+        .and_then(|code| StatusCode::from_u16(code).ok())
+        .ok_or(HttpError::UnexpectedError)?;
+    let mut response = Response::builder().status(status_code);
+    for HeaderPairRecord { name, value } in r.response_headers {
+        let name = HeaderName::try_from(name).map_err(|_| HttpError::UnexpectedError)?;
+        let value = HeaderValue::from_bytes(&value).map_err(|_| HttpError::UnexpectedError)?;
+        response = response.header(name, value);
+    }
+    let response = response
+        .body(Body::from(r.response_body))
+        .map_err(|_| HttpError::UnexpectedError)?;
+
+    Ok(Some(response))
+}
+
+/// Stores `response` against the claimed key and commits the request's
+/// transaction, handing back an equivalent response for the caller to send.
+#[tracing::instrument(name = "save a response", skip_all)]
+pub async fn save_response(
+    mut transaction: Transaction<'static, Postgres>,
+    idempotency_key: Option<&IdempotencyKey>,
+    username: &str,
+    response: Response,
+) -> Result<Response> {
+    let Some(idempotency_key) = idempotency_key else {
+        transaction
+            .commit()
+            .await
+            .map_err(HttpError::DatabaseError)?;
+        return Ok(response);
+    };
+
+    let (parts, body) = response.into_parts();
+    let body = to_bytes(body, usize::MAX).await.map_err(|e| {
+        tracing::error!("failed to read the response body: {e:?}");
+        HttpError::UnexpectedError
+    })?;
+    let status_code =
+        i16::try_from(parts.status.as_u16()).map_err(|_| HttpError::UnexpectedError)?;
+    let headers = parts
+        .headers
+        .iter()
+        .map(|(name, value)| HeaderPairRecord {
+            name: name.as_str().to_owned(),
+            value: value.as_bytes().to_owned(),
+        })
+        .collect::<Vec<_>>();
+
+    let query = sqlx::query_unchecked!(
+        r#"
+        UPDATE idempotency
+        SET
+            response_status_code = $3,
+            response_headers = $4,
+            response_body = $5
+        WHERE username = $1 AND idempotency_key = $2
+        "#,
+        username,
+        idempotency_key.as_ref(),
+        status_code,
+        headers,
+        body.as_ref()
+    );
+    transaction
+        .execute(query)
+        .await
+        .map_err(HttpError::DatabaseError)?;
+    transaction
+        .commit()
+        .await
+        .map_err(HttpError::DatabaseError)?;
+
+    Ok(Response::from_parts(parts, Body::from(body)))
+}
@@ -0,0 +1,693 @@
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+
+use secrecy::SecretString;
+use sqlx::{Executor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::EmailClient;
+use crate::configuration::Settings;
+use crate::domain::{
+    DeliveryStatus, NewsletterIssueStatus, SubscriberEmail, SubscriptionStatus, TrackingToken,
+    UnsubscribeToken,
+};
+use crate::email_client::{
+    EmailError, EmailHeader, MAX_BATCH_SIZE, OutgoingEmail, RetryPolicy, SuppressingSender,
+    check_batch_results,
+};
+use crate::email_templates::EmailTemplates;
+use crate::link_tracking::{self, rewrite_html_links, rewrite_text_links};
+use crate::startup::get_connection_pool;
+
+pub enum ExecutionOutcome {
+    TaskCompleted,
+    EmptyQueue,
+}
+
+struct Task {
+    newsletter_issue_id: Uuid,
+    subscriber_email: String,
+    n_retries: i16,
+}
+
+struct ConfirmedSubscriber {
+    id: Uuid,
+    name: String,
+}
+
+struct NewsletterIssue {
+    title: String,
+    text_content: String,
+    html_content: String,
+    track_opens: bool,
+    track_clicks: bool,
+}
+
+pub async fn run_worker_until_stopped(configuration: Settings) -> Result<(), std::io::Error> {
+    let db_pool = get_connection_pool(&configuration.database);
+    let retry_policy = configuration.email_client.retry_policy();
+    let email_client: EmailClient = Arc::new(SuppressingSender::new(
+        configuration.email_client.client(),
+        db_pool.clone(),
+    ));
+    let templates = EmailTemplates::load(&configuration.application.templates_directory)
+        .map_err(std::io::Error::other)?;
+    worker_loop(
+        db_pool,
+        email_client,
+        templates,
+        configuration.application.base_url,
+        configuration.application.hmac_secret,
+        retry_policy,
+    )
+    .await
+}
+
+async fn worker_loop(
+    pool: PgPool,
+    email_client: EmailClient,
+    templates: EmailTemplates,
+    base_url: String,
+    hmac_secret: SecretString,
+    retry_policy: RetryPolicy,
+) -> Result<(), std::io::Error> {
+    loop {
+        match try_execute_task(
+            &pool,
+            &email_client,
+            &templates,
+            &base_url,
+            &hmac_secret,
+            &retry_policy,
+        )
+        .await
+        {
+            Ok(ExecutionOutcome::EmptyQueue) => {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+            }
+            Err(_) => {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            Ok(ExecutionOutcome::TaskCompleted) => {}
+        }
+    }
+}
+
+/// Claims up to a batch of due delivery tasks and sends them in one
+/// [`send_batch`](crate::email_client::EmailSender::send_batch) call, recording
+/// the outcome of each.
+#[tracing::instrument(
+    name = "execute a batch of issue delivery tasks",
+    skip_all,
+    fields(n_tasks = tracing::field::Empty),
+    err
+)]
+pub async fn try_execute_task(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    templates: &EmailTemplates,
+    base_url: &str,
+    hmac_secret: &SecretString,
+    retry_policy: &RetryPolicy,
+) -> Result<ExecutionOutcome, sqlx::Error> {
+    let Some((mut transaction, tasks)) = dequeue_tasks(pool).await? else {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    };
+    tracing::Span::current().record("n_tasks", tasks.len());
+
+    let renderer = IssueRenderer {
+        templates,
+        base_url,
+        hmac_secret,
+    };
+    let mut issues = HashMap::new();
+    let mut completed = Vec::with_capacity(tasks.len());
+    let mut to_send = Vec::with_capacity(tasks.len());
+    let mut emails = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        match prepare_email(&mut transaction, &mut issues, &renderer, &task).await? {
+            PreparedEmail::Ready(email) => {
+                to_send.push(task);
+                emails.push(email);
+            }
+            PreparedEmail::Unsent(status, reason) => {
+                record_delivery_without_attempt(&mut transaction, &task, status, &reason).await?;
+                completed.push(task);
+            }
+        }
+    }
+
+    let mut retries = Vec::new();
+    if !emails.is_empty() {
+        let sent = email_client
+            .send_batch(&emails)
+            .await
+            .and_then(|results| check_batch_results(results, emails.len()));
+        // A failure of the whole batch is a failure for each of its emails.
+        let results: Vec<_> = match &sent {
+            Ok(results) => results
+                .iter()
+                .map(|result| result.as_ref().map(Option::as_deref))
+                .collect(),
+            Err(e) => vec![Err(e); to_send.len()],
+        };
+        for (task, result) in to_send.into_iter().zip(results) {
+            match record_send_outcome(&mut transaction, &task, result, retry_policy).await? {
+                TaskOutcome::Completed => completed.push(task),
+                TaskOutcome::Retry(delay) => retries.push((task, delay)),
+            }
+        }
+    }
+
+    for (task, delay) in &retries {
+        reschedule_task(&mut transaction, task, *delay).await?;
+    }
+    delete_tasks(&mut transaction, &completed).await?;
+    transaction.commit().await?;
+
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+/// What goes into every email of an issue besides the issue itself.
+struct IssueRenderer<'a> {
+    templates: &'a EmailTemplates,
+    base_url: &'a str,
+    hmac_secret: &'a SecretString,
+}
+
+/// An issue's content and its recorded links, by URL, fetched once per batch.
+struct IssueContent {
+    issue: NewsletterIssue,
+    links: HashMap<String, Uuid>,
+}
+
+enum PreparedEmail {
+    Ready(OutgoingEmail),
+    /// The issue will not be sent to the task's subscriber, and why.
+    Unsent(DeliveryStatus, String),
+}
+
+/// Whether a task is done with, or should be tried again after a delay.
+enum TaskOutcome {
+    Completed,
+    Retry(Duration),
+}
+
+#[tracing::instrument(
+    name = "prepare an issue delivery",
+    skip_all,
+    fields(
+        newsletter_issue_id = %task.newsletter_issue_id,
+        subscriber_email = %task.subscriber_email,
+    )
+)]
+async fn prepare_email(
+    transaction: &mut PgTransaction,
+    issues: &mut HashMap<Uuid, IssueContent>,
+    renderer: &IssueRenderer<'_>,
+    task: &Task,
+) -> Result<PreparedEmail, sqlx::Error> {
+    let Some(subscriber) = get_confirmed_subscriber(transaction, &task.subscriber_email).await?
+    else {
+        tracing::info!("skipping a subscriber who is no longer confirmed");
+        return Ok(PreparedEmail::Unsent(
+            DeliveryStatus::Skipped,
+            "the subscriber is no longer confirmed".into(),
+        ));
+    };
+    let email = match SubscriberEmail::parse(task.subscriber_email.clone()) {
+        Ok(email) => email,
+        Err(e) => {
+            tracing::error!(
+                "skipping a confirmed subscriber, their stored contact details are invalid: {e}"
+            );
+            return Ok(PreparedEmail::Unsent(
+                DeliveryStatus::Failed,
+                format!("the stored email address is invalid: {e}"),
+            ));
+        }
+    };
+    let IssueContent { issue, links } = match issues.entry(task.newsletter_issue_id) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => {
+            let issue = get_issue(transaction, task.newsletter_issue_id).await?;
+            let links = get_issue_links(transaction, task.newsletter_issue_id).await?;
+            entry.insert(IssueContent { issue, links })
+        }
+    };
+
+    let IssueRenderer {
+        templates,
+        base_url,
+        hmac_secret,
+    } = renderer;
+    let track_link = |url: &str| {
+        links.get(url).map(|link_id| {
+            let tracking_token = TrackingToken::for_link(
+                task.newsletter_issue_id,
+                subscriber.id,
+                *link_id,
+                hmac_secret,
+            );
+            format!("{base_url}/t/c/{}", tracking_token.as_ref())
+        })
+    };
+    let (html_content, text_content) = if issue.track_clicks {
+        (
+            rewrite_html_links(&issue.html_content, track_link),
+            rewrite_text_links(&issue.text_content, track_link),
+        )
+    } else {
+        (issue.html_content.clone(), issue.text_content.clone())
+    };
+    let unsubscribe_token = UnsubscribeToken::new(subscriber.id, hmac_secret);
+    let unsubscribe_link = format!(
+        "{base_url}/subscriptions/unsubscribe?token={}",
+        unsubscribe_token.as_ref()
+    );
+    let mut body = match templates.newsletter_issue(
+        &issue.title,
+        &subscriber.name,
+        &html_content,
+        &text_content,
+        &unsubscribe_link,
+    ) {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::error!("skipping a confirmed subscriber, the issue failed to render: {e}");
+            return Ok(PreparedEmail::Unsent(
+                DeliveryStatus::Failed,
+                format!("the issue failed to render: {e}"),
+            ));
+        }
+    };
+    if issue.track_opens {
+        let tracking_token =
+            TrackingToken::new(task.newsletter_issue_id, subscriber.id, hmac_secret);
+        body.add_open_pixel(&format!("{base_url}/t/o/{}.gif", tracking_token.as_ref()));
+    }
+    Ok(PreparedEmail::Ready(OutgoingEmail {
+        recipient: email,
+        subject: issue.title.clone(),
+        html_content: body.html,
+        text_content: body.text,
+        headers: vec![
+            EmailHeader::new("List-Unsubscribe", format!("<{unsubscribe_link}>")),
+            EmailHeader::new("List-Unsubscribe-Post", "List-Unsubscribe=One-Click"),
+        ],
+    }))
+}
+
+/// Records what the email provider made of the task's email.
+#[tracing::instrument(
+    name = "record an issue delivery outcome",
+    skip_all,
+    fields(
+        newsletter_issue_id = %task.newsletter_issue_id,
+        subscriber_email = %task.subscriber_email,
+    )
+)]
+async fn record_send_outcome(
+    transaction: &mut PgTransaction,
+    task: &Task,
+    sent: Result<Option<&str>, &EmailError>,
+    retry_policy: &RetryPolicy,
+) -> Result<TaskOutcome, sqlx::Error> {
+    match sent {
+        Ok(provider_message_id) => {
+            record_delivery_attempt(
+                transaction,
+                task,
+                DeliveryStatus::Sent,
+                provider_message_id,
+                None,
+            )
+            .await?;
+        }
+        Err(EmailError::Suppressed) => {
+            tracing::info!("skipping a confirmed subscriber whose address is suppressed");
+            record_delivery_without_attempt(
+                transaction,
+                task,
+                DeliveryStatus::Skipped,
+                "the address is on the suppression list",
+            )
+            .await?;
+        }
+        Err(e) => {
+            let error = e.to_string();
+            if let Some(delay) = retry_policy.delay(task.n_retries.unsigned_abs().into(), e) {
+                tracing::warn!(
+                    "failed to deliver issue to a confirmed subscriber, retrying in {delay:?}: {e:?}"
+                );
+                record_delivery_attempt(
+                    transaction,
+                    task,
+                    DeliveryStatus::Pending,
+                    None,
+                    Some(&error),
+                )
+                .await?;
+                return Ok(TaskOutcome::Retry(delay));
+            }
+            if e.is_transient() {
+                tracing::error!(
+                    "failed to deliver issue to a confirmed subscriber, giving up after {} retries: {e:?}",
+                    task.n_retries
+                );
+            } else {
+                tracing::error!(
+                    "failed to deliver issue to a confirmed subscriber, not retrying: {e:?}"
+                );
+            }
+            record_delivery_attempt(
+                transaction,
+                task,
+                DeliveryStatus::Failed,
+                None,
+                Some(&error),
+            )
+            .await?;
+        }
+    }
+    Ok(TaskOutcome::Completed)
+}
+
+type PgTransaction = Transaction<'static, Postgres>;
+
+#[tracing::instrument(name = "dequeue issue delivery tasks", skip_all)]
+async fn dequeue_tasks(pool: &PgPool) -> Result<Option<(PgTransaction, Vec<Task>)>, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    let tasks = sqlx::query_as!(
+        Task,
+        r#"
+        SELECT newsletter_issue_id, subscriber_email, n_retries
+        FROM issue_delivery_queue
+        WHERE execute_after <= NOW()
+        FOR UPDATE
+        SKIP LOCKED
+        LIMIT $1
+        "#,
+        i64::try_from(MAX_BATCH_SIZE).unwrap_or(i64::MAX),
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
+
+    if tasks.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some((transaction, tasks)))
+}
+
+#[tracing::instrument(name = "enqueue delivery tasks", skip_all)]
+pub async fn enqueue_delivery_tasks(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let query = sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_queue (newsletter_issue_id, subscriber_email)
+        SELECT $1, email
+        FROM subscriptions
+        WHERE status = $2
+        "#,
+        newsletter_issue_id,
+        SubscriptionStatus::Confirmed as SubscriptionStatus,
+    );
+    transaction.execute(query).await.map_err(|e| {
+        tracing::error!("execute enqueue_delivery_tasks: {e:?}");
+        e
+    })?;
+    let query = sqlx::query!(
+        r#"
+        INSERT INTO deliveries (newsletter_issue_id, subscriber_email)
+        SELECT newsletter_issue_id, subscriber_email
+        FROM issue_delivery_queue
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id,
+    );
+    transaction.execute(query).await.map_err(|e| {
+        tracing::error!("execute enqueue_delivery_tasks: {e:?}");
+        e
+    })?;
+    record_issue_links(transaction, newsletter_issue_id).await?;
+    // Nothing to wait for if there are no confirmed subscribers.
+    mark_issue_sent_if_delivered(transaction, newsletter_issue_id).await
+}
+
+/// Records the URLs the issue links to, the only ones its tracked links may
+/// redirect to. Issues that don't track clicks record none.
+#[tracing::instrument(name = "record issue links", skip_all)]
+async fn record_issue_links(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let content = sqlx::query!(
+        r#"
+        SELECT html_content, text_content, track_clicks
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id,
+    )
+    .fetch_one(&mut **transaction)
+    .await?;
+    if !content.track_clicks {
+        return Ok(());
+    }
+    let urls = link_tracking::links(&content.html_content, &content.text_content);
+    let link_ids: Vec<Uuid> = urls.iter().map(|_| Uuid::new_v4()).collect();
+
+    let query = sqlx::query!(
+        r#"
+        INSERT INTO issue_links (link_id, newsletter_issue_id, url)
+        SELECT link_id, $1, url
+        FROM UNNEST($2::UUID[], $3::TEXT[]) AS links (link_id, url)
+        ON CONFLICT (newsletter_issue_id, url) DO NOTHING
+        "#,
+        newsletter_issue_id,
+        &link_ids[..],
+        &urls[..],
+    );
+    transaction.execute(query).await.map_err(|e| {
+        tracing::error!("execute record_issue_links: {e:?}");
+        e
+    })?;
+    Ok(())
+}
+
+/// Deletes finished tasks and marks the issues they belonged to as sent if
+/// they were the last ones.
+#[tracing::instrument(name = "delete issue delivery tasks", skip_all)]
+async fn delete_tasks(transaction: &mut PgTransaction, tasks: &[Task]) -> Result<(), sqlx::Error> {
+    let issue_ids: BTreeSet<Uuid> = tasks.iter().map(|task| task.newsletter_issue_id).collect();
+    lock_issues(transaction, &issue_ids).await?;
+
+    let (task_issue_ids, emails): (Vec<Uuid>, Vec<String>) = tasks
+        .iter()
+        .map(|task| (task.newsletter_issue_id, task.subscriber_email.clone()))
+        .unzip();
+    let query = sqlx::query!(
+        r#"
+        DELETE FROM issue_delivery_queue
+        WHERE (newsletter_issue_id, subscriber_email) IN (
+            SELECT * FROM UNNEST($1::UUID[], $2::TEXT[])
+        )
+        "#,
+        &task_issue_ids[..],
+        &emails[..],
+    );
+    transaction.execute(query).await?;
+
+    for newsletter_issue_id in issue_ids {
+        mark_issue_sent_if_delivered(transaction, newsletter_issue_id).await?;
+    }
+    Ok(())
+}
+
+/// Locks the issues' rows until the transaction ends, so workers deleting the
+/// last tasks of an issue take turns. Otherwise each could still see the
+/// other's tasks and neither would mark the issue as sent.
+#[tracing::instrument(name = "lock newsletter issues", skip_all)]
+async fn lock_issues(
+    transaction: &mut PgTransaction,
+    newsletter_issue_ids: &BTreeSet<Uuid>,
+) -> Result<(), sqlx::Error> {
+    let newsletter_issue_ids: Vec<Uuid> = newsletter_issue_ids.iter().copied().collect();
+    // Always lock in the same order so two workers cannot deadlock.
+    sqlx::query!(
+        r#"
+        SELECT newsletter_issue_id
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = ANY($1)
+        ORDER BY newsletter_issue_id
+        FOR UPDATE
+        "#,
+        &newsletter_issue_ids[..],
+    )
+    .fetch_all(&mut **transaction)
+    .await?;
+    Ok(())
+}
+
+/// Moves a sending issue to sent once it has no delivery tasks left.
+#[tracing::instrument(name = "mark a newsletter issue as sent", skip_all)]
+async fn mark_issue_sent_if_delivered(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let query = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = $2, updated_at = NOW()
+        WHERE
+            newsletter_issue_id = $1
+            AND status = $3
+            AND NOT EXISTS (
+                SELECT 1 FROM issue_delivery_queue WHERE newsletter_issue_id = $1
+            )
+        "#,
+        newsletter_issue_id,
+        NewsletterIssueStatus::Sent as NewsletterIssueStatus,
+        NewsletterIssueStatus::Sending as NewsletterIssueStatus,
+    );
+    transaction.execute(query).await?;
+    Ok(())
+}
+
+/// Records one attempt at sending the issue to the task's subscriber.
+#[tracing::instrument(name = "record a delivery attempt", skip_all)]
+async fn record_delivery_attempt(
+    transaction: &mut PgTransaction,
+    task: &Task,
+    status: DeliveryStatus,
+    provider_message_id: Option<&str>,
+    error: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    let query = sqlx::query!(
+        r#"
+        UPDATE deliveries
+        SET
+            status = $3,
+            n_attempts = n_attempts + 1,
+            provider_message_id = $4,
+            last_error = $5,
+            updated_at = NOW()
+        WHERE newsletter_issue_id = $1 AND subscriber_email = $2
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_email,
+        status as DeliveryStatus,
+        provider_message_id,
+        error,
+    );
+    transaction.execute(query).await?;
+    Ok(())
+}
+
+/// Records why the issue was never sent to the task's subscriber.
+#[tracing::instrument(name = "record a delivery without an attempt", skip_all)]
+async fn record_delivery_without_attempt(
+    transaction: &mut PgTransaction,
+    task: &Task,
+    status: DeliveryStatus,
+    reason: &str,
+) -> Result<(), sqlx::Error> {
+    let query = sqlx::query!(
+        r#"
+        UPDATE deliveries
+        SET status = $3, last_error = $4, updated_at = NOW()
+        WHERE newsletter_issue_id = $1 AND subscriber_email = $2
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_email,
+        status as DeliveryStatus,
+        reason,
+    );
+    transaction.execute(query).await?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "reschedule an issue delivery task", skip_all)]
+async fn reschedule_task(
+    transaction: &mut PgTransaction,
+    task: &Task,
+    delay: Duration,
+) -> Result<(), sqlx::Error> {
+    let query = sqlx::query!(
+        r#"
+        UPDATE issue_delivery_queue
+        SET
+            n_retries = n_retries + 1,
+            execute_after = NOW() + MAKE_INTERVAL(secs => $3)
+        WHERE newsletter_issue_id = $1 AND subscriber_email = $2
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_email,
+        delay.as_secs_f64(),
+    );
+    transaction.execute(query).await?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "get a confirmed subscriber", skip_all)]
+async fn get_confirmed_subscriber(
+    transaction: &mut PgTransaction,
+    subscriber_email: &str,
+) -> Result<Option<ConfirmedSubscriber>, sqlx::Error> {
+    sqlx::query_as!(
+        ConfirmedSubscriber,
+        r#"
+        SELECT id, name
+        FROM subscriptions
+        WHERE email = $1 AND status = $2
+        "#,
+        subscriber_email,
+        SubscriptionStatus::Confirmed as SubscriptionStatus,
+    )
+    .fetch_optional(&mut **transaction)
+    .await
+}
+
+#[tracing::instrument(name = "get a newsletter issue", skip_all)]
+async fn get_issue(
+    transaction: &mut PgTransaction,
+    newsletter_issue_id: Uuid,
+) -> Result<NewsletterIssue, sqlx::Error> {
+    sqlx::query_as!(
+        NewsletterIssue,
+        r#"
+        SELECT title, text_content, html_content, track_opens, track_clicks
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_one(&mut **transaction)
+    .await
+}
+
+/// The issue's recorded links, by URL.
+#[tracing::instrument(name = "get issue links", skip_all)]
+async fn get_issue_links(
+    transaction: &mut PgTransaction,
+    newsletter_issue_id: Uuid,
+) -> Result<HashMap<String, Uuid>, sqlx::Error> {
+    let links = sqlx::query!(
+        r#"
+        SELECT link_id, url
+        FROM issue_links
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_all(&mut **transaction)
+    .await?;
+    Ok(links
+        .into_iter()
+        .map(|link| (link.url, link.link_id))
+        .collect())
+}
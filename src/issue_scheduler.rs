@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+
+use crate::clock::{Clock, SystemClock};
+use crate::configuration::Settings;
+use crate::domain::NewsletterIssueStatus;
+use crate::issue_delivery_worker::{ExecutionOutcome, enqueue_delivery_tasks};
+use crate::startup::get_connection_pool;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+pub async fn run_scheduler_until_stopped(configuration: Settings) -> Result<(), std::io::Error> {
+    let db_pool = get_connection_pool(&configuration.database);
+    let clock = SystemClock;
+    loop {
+        let outcome = try_promote_due_issue(&db_pool, &clock).await;
+        if !matches!(outcome, Ok(ExecutionOutcome::TaskCompleted)) {
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
+
+/// Moves the scheduled issue that has been due the longest, if any, into the
+/// delivery queue, as if it had been published by hand at `clock.now()`.
+#[tracing::instrument(
+    name = "promote a scheduled issue",
+    skip_all,
+    fields(newsletter_issue_id = tracing::field::Empty),
+    err
+)]
+pub async fn try_promote_due_issue(
+    pool: &PgPool,
+    clock: &impl Clock,
+) -> Result<ExecutionOutcome, sqlx::Error> {
+    let now = clock.now();
+    let mut transaction = pool.begin().await?;
+    let issue = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = $2, published_at = $1, updated_at = $1
+        WHERE newsletter_issue_id = (
+            SELECT newsletter_issue_id
+            FROM newsletter_issues
+            WHERE status = $3 AND scheduled_for <= $1
+            ORDER BY scheduled_for
+            FOR UPDATE
+            SKIP LOCKED
+            LIMIT 1
+        )
+        RETURNING newsletter_issue_id
+        "#,
+        now,
+        NewsletterIssueStatus::Sending as NewsletterIssueStatus,
+        NewsletterIssueStatus::Scheduled as NewsletterIssueStatus,
+    )
+    .fetch_optional(&mut *transaction)
+    .await?;
+    let Some(issue) = issue else {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    };
+    let newsletter_issue_id = issue.newsletter_issue_id;
+    tracing::Span::current().record(
+        "newsletter_issue_id",
+        tracing::field::display(newsletter_issue_id),
+    );
+
+    enqueue_delivery_tasks(&mut transaction, newsletter_issue_id).await?;
+    transaction.commit().await?;
+
+    Ok(ExecutionOutcome::TaskCompleted)
+}
@@ -3,14 +3,25 @@
     clippy::missing_panics_doc,
     clippy::must_use_candidate
 )]
+pub mod authentication;
+pub mod clock;
 pub mod configuration;
 pub mod domain;
 pub mod email_client;
+pub mod email_outbox_worker;
+pub mod email_templates;
 pub mod error;
+pub mod idempotency;
+pub mod issue_delivery_worker;
+pub mod issue_scheduler;
+pub mod link_tracking;
 pub mod request_id;
 pub mod routes;
+pub mod session_state;
+pub mod session_store;
 pub mod startup;
+pub mod subscription_cleanup_worker;
 pub mod telemetry;
 
-pub use email_client::EmailClient;
+pub use email_client::{EmailClient, EmailSender};
 pub use startup::Application;
@@ -0,0 +1,265 @@
+//! Finds the links in an issue's bodies and rewrites them, so every click
+//! goes through the click tracking redirect first.
+//!
+//! Only absolute `http` and `https` URLs are considered, `mailto:` links and
+//! relative or in-page links are left alone.
+
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// The distinct URLs linked from `html` and `text`, in the order they first
+/// appear.
+pub fn links(html: &str, text: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    html_links(html)
+        .into_iter()
+        .chain(text_links(text))
+        .map(|(_, url)| url)
+        .filter(|url| seen.insert(url.clone()))
+        .collect()
+}
+
+/// Replaces the `href` of each `<a>` tag with `rewrite`'s result for its URL,
+/// links it returns `None` for are kept as they are.
+pub fn rewrite_html_links(html: &str, rewrite: impl Fn(&str) -> Option<String>) -> String {
+    replace(html, html_links(html), |url| {
+        rewrite(url).map(|replacement| htmlescape::encode_minimal(&replacement))
+    })
+}
+
+/// Replaces each bare URL in `text` with `rewrite`'s result for it, URLs it
+/// returns `None` for are kept as they are.
+pub fn rewrite_text_links(text: &str, rewrite: impl Fn(&str) -> Option<String>) -> String {
+    replace(text, text_links(text), rewrite)
+}
+
+fn replace(
+    source: &str,
+    links: Vec<(Range<usize>, String)>,
+    rewrite: impl Fn(&str) -> Option<String>,
+) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut copied = 0;
+    for (range, url) in links {
+        if let Some(replacement) = rewrite(&url) {
+            out.push_str(&source[copied..range.start]);
+            out.push_str(&replacement);
+            copied = range.end;
+        }
+    }
+    out.push_str(&source[copied..]);
+    out
+}
+
+fn is_trackable(url: &str) -> bool {
+    let url = url.to_ascii_lowercase();
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+/// The URL in the `href` of each `<a>` tag, unescaped, along with where its
+/// escaped form sits in `html`.
+fn html_links(html: &str) -> Vec<(Range<usize>, String)> {
+    // Lowercasing ASCII leaves every byte offset where it was.
+    let lower = html.to_ascii_lowercase();
+    let mut links = Vec::new();
+    let mut position = 0;
+    while let Some(found) = lower[position..].find("<a") {
+        let name_end = position + found + 2;
+        let Some(tag_length) = lower[name_end..].find('>') else {
+            break;
+        };
+        let tag_end = name_end + tag_length;
+        position = name_end;
+        // Skip `<abbr>`, `<area>` and the like.
+        if !lower[name_end..].starts_with(|c: char| c.is_ascii_whitespace()) {
+            continue;
+        }
+        let Some(range) = href_value(&lower, name_end, tag_end) else {
+            continue;
+        };
+        let escaped = &html[range.clone()];
+        let url = htmlescape::decode_html(escaped).unwrap_or_else(|_| escaped.to_owned());
+        if is_trackable(&url) {
+            links.push((range, url));
+        }
+    }
+    links
+}
+
+/// Where the value of the `href` attribute is in the tag between `start`
+/// and `end`, without its quotes.
+fn href_value(lower: &str, start: usize, end: usize) -> Option<Range<usize>> {
+    let mut position = start;
+    while let Some(found) = lower[position..end].find("href") {
+        let name_start = position + found;
+        position = name_start + "href".len();
+        if !lower[..name_start].ends_with(|c: char| c.is_ascii_whitespace()) {
+            continue;
+        }
+        let Some(value) = lower[position..end].trim_start().strip_prefix('=') else {
+            continue;
+        };
+        let value = value.trim_start();
+        let value_start = end - value.len();
+        if let Some(quote @ ('"' | '\'')) = value.chars().next() {
+            let value_end = value[1..].find(quote).map_or(end, |n| value_start + 1 + n);
+            return Some(value_start + 1..value_end);
+        }
+        let value_end = value
+            .find(|c: char| c.is_ascii_whitespace())
+            .map_or(end, |n| value_start + n);
+        return Some(value_start..value_end);
+    }
+    None
+}
+
+/// Bare `http` and `https` URLs in `text` and where they are.
+fn text_links(text: &str) -> Vec<(Range<usize>, String)> {
+    let mut links = Vec::new();
+    let mut position = 0;
+    while let Some(found) = text[position..].find("http") {
+        let start = position + found;
+        position = start + "http".len();
+        let rest = &text[start..];
+        let scheme_length = if rest.starts_with("https://") {
+            "https://".len()
+        } else if rest.starts_with("http://") {
+            "http://".len()
+        } else {
+            continue;
+        };
+        if text[..start].ends_with(|c: char| c.is_alphanumeric()) {
+            continue;
+        }
+        let length = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"'))
+            .unwrap_or(rest.len());
+        let url = trim_trailing_punctuation(&rest[..length]);
+        if url.len() > scheme_length {
+            position = start + url.len();
+            links.push((start..position, url.to_owned()));
+        }
+    }
+    links
+}
+
+/// Drops punctuation that more likely ends the sentence than the URL, and a
+/// closing parenthesis with no opening one in the URL, as in `site (URL)`.
+fn trim_trailing_punctuation(url: &str) -> &str {
+    let mut url = url;
+    loop {
+        let trimmed = url.trim_end_matches(['.', ',', ';', ':', '!', '?', '\'']);
+        let trimmed = match trimmed.strip_suffix(')') {
+            Some(inner) if inner.matches('(').count() < trimmed.matches(')').count() => inner,
+            _ => trimmed,
+        };
+        if trimmed.len() == url.len() {
+            return url;
+        }
+        url = trimmed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(url: &str) -> Option<String> {
+        (url != "https://example.com/untracked").then(|| "https://t.example/c/1".to_owned())
+    }
+
+    #[test]
+    fn html_links_are_rewritten() {
+        let html = r#"<p>Read <a href="https://example.com/post">the post</a>.</p>"#;
+
+        assert_eq!(
+            rewrite_html_links(html, track),
+            r#"<p>Read <a href="https://t.example/c/1">the post</a>.</p>"#
+        );
+    }
+
+    #[test]
+    fn html_links_are_found_whatever_the_quoting_and_case() {
+        let html = concat!(
+            r#"<A HREF='https://example.com/a'>a</A>"#,
+            r#"<a title="b" href=https://example.com/b>b</a>"#,
+            r#"<a class="x" href = "https://example.com/c">c</a>"#,
+        );
+
+        assert_eq!(
+            links(html, ""),
+            [
+                "https://example.com/a",
+                "https://example.com/b",
+                "https://example.com/c"
+            ]
+        );
+    }
+
+    #[test]
+    fn html_links_are_unescaped() {
+        let html = r#"<a href="https://example.com/?a=1&amp;b=2">x</a>"#;
+
+        assert_eq!(links(html, ""), ["https://example.com/?a=1&b=2"]);
+    }
+
+    #[test]
+    fn other_links_and_tags_are_left_alone() {
+        let html = concat!(
+            r#"<a href="mailto:ursula@example.com">mail</a>"#,
+            r##"<a href="#top">top</a>"##,
+            r#"<abbr title="https://example.com">abbr</abbr>"#,
+            r#"<a data-href="https://example.com/data">data</a>"#,
+            r#"<img src="https://example.com/image.png">"#,
+        );
+
+        assert!(links(html, "").is_empty());
+        assert_eq!(rewrite_html_links(html, track), html);
+    }
+
+    #[test]
+    fn links_the_rewrite_declines_are_kept() {
+        let html = r#"<a href="https://example.com/untracked">x</a>"#;
+
+        assert_eq!(rewrite_html_links(html, track), html);
+    }
+
+    #[test]
+    fn bare_urls_in_text_are_rewritten() {
+        let text =
+            "Read the post (https://example.com/post).\nOr https://example.com/other, later.";
+
+        assert_eq!(
+            rewrite_text_links(text, track),
+            "Read the post (https://t.example/c/1).\nOr https://t.example/c/1, later."
+        );
+    }
+
+    #[test]
+    fn text_urls_keep_balanced_parentheses() {
+        let text = "See https://en.wikipedia.org/wiki/Earthsea_(series).";
+
+        assert_eq!(
+            links("", text),
+            ["https://en.wikipedia.org/wiki/Earthsea_(series)"]
+        );
+    }
+
+    #[test]
+    fn words_that_merely_start_with_http_are_not_urls() {
+        let text = "httpbin, http:// and xhttp://example.com are not links.";
+
+        assert!(links("", text).is_empty());
+    }
+
+    #[test]
+    fn links_are_listed_once() {
+        let html = r#"<a href="https://example.com/a">a</a>"#;
+        let text = "a (https://example.com/a) https://example.com/b";
+
+        assert_eq!(
+            links(html, text),
+            ["https://example.com/a", "https://example.com/b"]
+        );
+    }
+}
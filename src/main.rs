@@ -1,7 +1,13 @@
+use std::fmt::{Debug, Display};
 use std::io::IsTerminal;
 
+use bulletin::email_outbox_worker::run_dispatcher_until_stopped;
+use bulletin::issue_delivery_worker::run_worker_until_stopped;
+use bulletin::issue_scheduler::run_scheduler_until_stopped;
+use bulletin::subscription_cleanup_worker::run_cleanup_until_stopped;
 use bulletin::telemetry::{Formatter, get_subscriber, init_subscriber};
 use bulletin::{Application, configuration};
+use tokio::task::JoinError;
 
 const PACKAGE_NAME: &str = env!("CARGO_PKG_NAME");
 
@@ -25,6 +31,34 @@ async fn main() -> Result<(), std::io::Error> {
     init_subscriber(subscriber);
 
     let configuration = configuration::get().expect("failed to read configuration");
-    let application = Application::build(configuration)?;
-    application.run_until_stopped().await
+    let application = Application::build(configuration.clone()).await?;
+    let application_task = tokio::spawn(application.run_until_stopped());
+    let worker_task = tokio::spawn(run_worker_until_stopped(configuration.clone()));
+    let dispatcher_task = tokio::spawn(run_dispatcher_until_stopped(configuration.clone()));
+    let scheduler_task = tokio::spawn(run_scheduler_until_stopped(configuration.clone()));
+    let cleanup_task = tokio::spawn(run_cleanup_until_stopped(configuration));
+
+    tokio::select! {
+        outcome = application_task => report_exit("API", outcome),
+        outcome = worker_task => report_exit("background worker", outcome),
+        outcome = dispatcher_task => report_exit("outbox dispatcher", outcome),
+        outcome = scheduler_task => report_exit("issue scheduler", outcome),
+        outcome = cleanup_task => report_exit("cleanup worker", outcome),
+    }
+
+    Ok(())
+}
+
+fn report_exit(task_name: &str, outcome: Result<Result<(), impl Debug + Display>, JoinError>) {
+    match outcome {
+        Ok(Ok(())) => {
+            tracing::info!("{task_name} has exited");
+        }
+        Ok(Err(e)) => {
+            tracing::error!(error.cause_chain = ?e, error.message = %e, "{task_name} failed");
+        }
+        Err(e) => {
+            tracing::error!(error.cause_chain = ?e, error.message = %e, "{task_name} task failed to complete");
+        }
+    }
 }
@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use axum::Extension;
+use axum::extract::State;
+use axum::response::Html;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::authentication::UserId;
+use crate::error::{HttpError, Result};
+use crate::startup::AppState;
+
+#[tracing::instrument(name = "GET - admin dashboard", skip_all, fields(user_id = %user_id))]
+pub async fn admin_dashboard(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+) -> Result<Html<String>> {
+    let username = get_username(&state.db_pool, *user_id)
+        .await
+        .map_err(HttpError::DatabaseError)?;
+    let username = htmlescape::encode_minimal(&username);
+
+    Ok(Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Admin dashboard</title>
+</head>
+<body>
+    <p>Welcome {username}!</p>
+    <p>Available actions:</p>
+    <ol>
+        <li><a href="/admin/issues">Newsletter issues</a></li>
+        <li><a href="/admin/suppressions">Suppression list</a></li>
+        <li><a href="/admin/password">Change password</a></li>
+        <li>
+            <form name="logoutForm" action="/admin/logout" method="post">
+                <input type="submit" value="Logout">
+            </form>
+        </li>
+    </ol>
+</body>
+</html>"#,
+    )))
+}
+
+#[tracing::instrument(name = "get username", skip_all)]
+pub async fn get_username(pool: &PgPool, user_id: Uuid) -> Result<String, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT username
+        FROM users
+        WHERE user_id = $1
+        "#,
+        user_id,
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("failed to execute query: {e:?}");
+        e
+    })?;
+    Ok(row.username)
+}
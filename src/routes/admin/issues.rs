@@ -0,0 +1,865 @@
+use std::fmt::Write;
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::{Extension, Form};
+use axum_messages::Messages;
+use serde::Deserialize;
+use sqlx::types::chrono::{DateTime, Utc};
+use sqlx::{Executor, PgPool};
+use uuid::Uuid;
+
+use super::dashboard::get_username;
+use crate::authentication::UserId;
+use crate::domain::{DeliveryStatus, NewsletterContent, NewsletterIssue, NewsletterIssueStatus};
+use crate::error::{HttpError, Result};
+use crate::issue_delivery_worker::enqueue_delivery_tasks;
+use crate::routes::flash_messages::messages_html;
+use crate::startup::AppState;
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M UTC";
+
+#[derive(Deserialize)]
+pub struct ScheduleFormData {
+    /// An RFC 3339 timestamp, e.g. `2026-10-18T09:00:00Z`.
+    scheduled_for: String,
+}
+
+#[derive(Deserialize)]
+pub struct IssueFormData {
+    title: String,
+    markdown: String,
+    /// Only sent when the checkbox is ticked.
+    #[serde(default)]
+    track_opens: bool,
+    /// Only sent when the checkbox is ticked.
+    #[serde(default)]
+    track_clicks: bool,
+}
+
+/// How many of an issue's deliveries are in each status.
+struct DeliverySummary {
+    pending: i64,
+    sent: i64,
+    failed: i64,
+    skipped: i64,
+    /// Deliveries opened at least once.
+    opened: i64,
+    /// Every recorded open, repeat opens included.
+    opens: i64,
+    /// Deliveries with at least one tracked link followed.
+    clicked: i64,
+    clicks: i64,
+}
+
+impl DeliverySummary {
+    fn open_rate(&self) -> String {
+        share_of(self.opened, self.sent)
+    }
+
+    fn click_rate(&self) -> String {
+        share_of(self.clicked, self.sent)
+    }
+}
+
+struct LinkClicks {
+    url: String,
+    n_clicks: i32,
+}
+
+struct FailedDelivery {
+    subscriber_email: String,
+    n_attempts: i16,
+    last_error: Option<String>,
+}
+
+struct IssueSummary {
+    newsletter_issue_id: Uuid,
+    title: String,
+    status: NewsletterIssueStatus,
+    updated_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "GET - list newsletter issues", skip_all)]
+pub async fn list_issues(
+    State(state): State<Arc<AppState>>,
+    messages: Messages,
+) -> Result<Html<String>> {
+    let issues = get_issue_summaries(&state.db_pool)
+        .await
+        .map_err(HttpError::DatabaseError)?;
+
+    let mut rows_html = String::new();
+    for issue in issues {
+        let _ = writeln!(
+            rows_html,
+            r#"<tr><td><a href="/admin/issues/{id}">{title}</a></td><td>{status}</td><td>{updated_at}</td><td><a href="/admin/issues/{id}/preview">Preview</a></td></tr>"#,
+            id = issue.newsletter_issue_id,
+            title = htmlescape::encode_minimal(&issue.title),
+            status = issue.status,
+            updated_at = issue.updated_at.format(TIME_FORMAT),
+        );
+    }
+
+    Ok(Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Newsletter issues</title>
+</head>
+<body>
+    {message_html}
+    <p><a href="/admin/issues/new">Write a new issue</a></p>
+    <table>
+        <tr><th>Title</th><th>Status</th><th>Last updated</th><th></th></tr>
+        {rows_html}
+    </table>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+        message_html = messages_html(messages),
+    )))
+}
+
+pub async fn new_issue_form(messages: Messages) -> Html<String> {
+    Html(issue_page(
+        "New issue",
+        &messages_html(messages),
+        &issue_form("/admin/issues", "", "", false, false, "Save draft"),
+    ))
+}
+
+#[tracing::instrument(name = "POST - create a draft issue", skip_all, fields(user_id = %user_id))]
+pub async fn create_issue(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    messages: Messages,
+    Form(form): Form<IssueFormData>,
+) -> Result<Response> {
+    if form.title.trim().is_empty() {
+        messages.error("The issue needs a title.");
+        return Ok(Redirect::to("/admin/issues/new").into_response());
+    }
+
+    let content = NewsletterContent::from_markdown(&form.markdown);
+    let newsletter_issue_id = insert_draft(
+        &state.db_pool,
+        *user_id,
+        &form.title,
+        &form.markdown,
+        &content,
+        form.track_opens,
+        form.track_clicks,
+    )
+    .await
+    .map_err(HttpError::DatabaseError)?;
+
+    messages.info("Your draft has been saved.");
+    Ok(Redirect::to(&format!("/admin/issues/{newsletter_issue_id}")).into_response())
+}
+
+#[tracing::instrument(
+    name = "GET - edit a newsletter issue",
+    skip_all,
+    fields(newsletter_issue_id = %newsletter_issue_id)
+)]
+pub async fn edit_issue_form(
+    State(state): State<Arc<AppState>>,
+    Path(newsletter_issue_id): Path<Uuid>,
+    messages: Messages,
+) -> Result<Html<String>> {
+    let issue = get_issue(&state.db_pool, newsletter_issue_id)
+        .await
+        .map_err(HttpError::DatabaseError)?
+        .ok_or(HttpError::NotFound)?;
+
+    let action = format!("/admin/issues/{newsletter_issue_id}");
+    let title = htmlescape::encode_minimal(&issue.title);
+    let body = match (issue.status, issue.scheduled_for) {
+        (NewsletterIssueStatus::Draft, _) => format!(
+            r#"{form}
+    <form action="/admin/issues/{newsletter_issue_id}/publish" method="post">
+        <button type="submit">Publish now</button>
+    </form>
+    {schedule_form}"#,
+            form = issue_form(
+                &action,
+                &issue.title,
+                issue.markdown_content.as_deref().unwrap_or_default(),
+                issue.track_opens,
+                issue.track_clicks,
+                "Save draft",
+            ),
+            schedule_form = schedule_form(newsletter_issue_id, None, "Schedule"),
+        ),
+        (NewsletterIssueStatus::Scheduled, Some(scheduled_for)) => format!(
+            r#"<p>{title} is scheduled for {scheduled_at}.</p>
+    {schedule_form}
+    <form action="/admin/issues/{newsletter_issue_id}/cancel" method="post">
+        <button type="submit">Cancel and go back to draft</button>
+    </form>"#,
+            scheduled_at = scheduled_for.format(TIME_FORMAT),
+            schedule_form = schedule_form(newsletter_issue_id, Some(scheduled_for), "Reschedule"),
+        ),
+        (status, _) => format!(
+            r#"<p>{title} is {status} and can no longer be edited.</p>
+    <p><a href="/admin/issues/{newsletter_issue_id}/deliveries">Deliveries</a></p>"#
+        ),
+    };
+    let body = format!(
+        r#"{body}
+    <p><a href="/admin/issues/{newsletter_issue_id}/preview">Preview</a></p>"#
+    );
+
+    Ok(Html(issue_page(
+        "Edit issue",
+        &messages_html(messages),
+        &body,
+    )))
+}
+
+#[tracing::instrument(
+    name = "POST - update a draft issue",
+    skip_all,
+    fields(newsletter_issue_id = %newsletter_issue_id)
+)]
+pub async fn update_issue(
+    State(state): State<Arc<AppState>>,
+    Path(newsletter_issue_id): Path<Uuid>,
+    messages: Messages,
+    Form(form): Form<IssueFormData>,
+) -> Result<Response> {
+    let issue = get_issue(&state.db_pool, newsletter_issue_id)
+        .await
+        .map_err(HttpError::DatabaseError)?
+        .ok_or(HttpError::NotFound)?;
+    if !issue.status.is_editable() {
+        return Err(HttpError::Conflict(format!(
+            "issue {newsletter_issue_id} is {}",
+            issue.status
+        )))?;
+    }
+
+    let edit_page = format!("/admin/issues/{newsletter_issue_id}");
+    if form.title.trim().is_empty() {
+        messages.error("The issue needs a title.");
+        return Ok(Redirect::to(&edit_page).into_response());
+    }
+
+    let content = NewsletterContent::from_markdown(&form.markdown);
+    let updated = update_draft(
+        &state.db_pool,
+        newsletter_issue_id,
+        &form.title,
+        &form.markdown,
+        &content,
+        form.track_opens,
+        form.track_clicks,
+    )
+    .await
+    .map_err(HttpError::DatabaseError)?;
+    if !updated {
+        // Published between the check above and the update.
+        return Err(HttpError::Conflict(format!(
+            "issue {newsletter_issue_id} is no longer a draft"
+        )))?;
+    }
+
+    messages.info("Your draft has been saved.");
+    Ok(Redirect::to(&edit_page).into_response())
+}
+
+/// Renders an issue the way subscribers will see it, addressed to the admin.
+#[tracing::instrument(
+    name = "GET - preview a newsletter issue",
+    skip_all,
+    fields(newsletter_issue_id = %newsletter_issue_id)
+)]
+pub async fn preview_issue(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    Path(newsletter_issue_id): Path<Uuid>,
+) -> Result<Html<String>> {
+    let issue = get_issue(&state.db_pool, newsletter_issue_id)
+        .await
+        .map_err(HttpError::DatabaseError)?
+        .ok_or(HttpError::NotFound)?;
+    let username = get_username(&state.db_pool, *user_id)
+        .await
+        .map_err(HttpError::DatabaseError)?;
+
+    let email = state.templates.newsletter_issue(
+        &issue.title,
+        &username,
+        &issue.html_content,
+        &issue.text_content,
+        "#",
+    )?;
+    Ok(Html(email.html))
+}
+
+#[tracing::instrument(
+    name = "POST - publish a draft issue",
+    skip_all,
+    fields(newsletter_issue_id = %newsletter_issue_id)
+)]
+pub async fn publish_issue(
+    State(state): State<Arc<AppState>>,
+    Path(newsletter_issue_id): Path<Uuid>,
+    messages: Messages,
+) -> Result<Response> {
+    let mut transaction = state
+        .db_pool
+        .begin()
+        .await
+        .map_err(HttpError::DatabaseError)?;
+    let query = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = $2, published_at = NOW(), updated_at = NOW()
+        WHERE newsletter_issue_id = $1 AND status = $3
+        "#,
+        newsletter_issue_id,
+        NewsletterIssueStatus::Sending as NewsletterIssueStatus,
+        NewsletterIssueStatus::Draft as NewsletterIssueStatus,
+    );
+    let published = transaction
+        .execute(query)
+        .await
+        .map_err(HttpError::DatabaseError)?;
+    if published.rows_affected() == 0 {
+        return Err(transition_error(&state.db_pool, newsletter_issue_id).await?)?;
+    }
+
+    enqueue_delivery_tasks(&mut transaction, newsletter_issue_id)
+        .await
+        .map_err(HttpError::DatabaseError)?;
+    transaction
+        .commit()
+        .await
+        .map_err(HttpError::DatabaseError)?;
+
+    messages.info("The issue has been published and will go out shortly.");
+    Ok(Redirect::to("/admin/issues").into_response())
+}
+
+#[tracing::instrument(
+    name = "POST - schedule an issue",
+    skip_all,
+    fields(newsletter_issue_id = %newsletter_issue_id)
+)]
+pub async fn schedule_issue(
+    State(state): State<Arc<AppState>>,
+    Path(newsletter_issue_id): Path<Uuid>,
+    messages: Messages,
+    Form(form): Form<ScheduleFormData>,
+) -> Result<Response> {
+    let edit_page = format!("/admin/issues/{newsletter_issue_id}");
+    let Ok(scheduled_for) = DateTime::parse_from_rfc3339(form.scheduled_for.trim()) else {
+        messages.error("Enter the delivery time as e.g. 2026-10-18T09:00:00Z.");
+        return Ok(Redirect::to(&edit_page).into_response());
+    };
+    let scheduled_for = scheduled_for.with_timezone(&Utc);
+    if scheduled_for <= Utc::now() {
+        messages.error("The delivery time must be in the future.");
+        return Ok(Redirect::to(&edit_page).into_response());
+    }
+
+    let query = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = $3, scheduled_for = $2, updated_at = NOW()
+        WHERE newsletter_issue_id = $1 AND (status = $3 OR status = $4)
+        "#,
+        newsletter_issue_id,
+        scheduled_for,
+        NewsletterIssueStatus::Scheduled as NewsletterIssueStatus,
+        NewsletterIssueStatus::Draft as NewsletterIssueStatus,
+    );
+    let scheduled = state
+        .db_pool
+        .execute(query)
+        .await
+        .map_err(HttpError::DatabaseError)?;
+    if scheduled.rows_affected() == 0 {
+        return Err(transition_error(&state.db_pool, newsletter_issue_id).await?)?;
+    }
+
+    messages.info(format!(
+        "The issue will go out at {}.",
+        scheduled_for.format(TIME_FORMAT)
+    ));
+    Ok(Redirect::to(&edit_page).into_response())
+}
+
+#[tracing::instrument(
+    name = "POST - cancel a scheduled issue",
+    skip_all,
+    fields(newsletter_issue_id = %newsletter_issue_id)
+)]
+pub async fn cancel_scheduled_issue(
+    State(state): State<Arc<AppState>>,
+    Path(newsletter_issue_id): Path<Uuid>,
+    messages: Messages,
+) -> Result<Response> {
+    let query = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = $2, scheduled_for = NULL, updated_at = NOW()
+        WHERE newsletter_issue_id = $1 AND status = $3
+        "#,
+        newsletter_issue_id,
+        NewsletterIssueStatus::Draft as NewsletterIssueStatus,
+        NewsletterIssueStatus::Scheduled as NewsletterIssueStatus,
+    );
+    let cancelled = state
+        .db_pool
+        .execute(query)
+        .await
+        .map_err(HttpError::DatabaseError)?;
+    if cancelled.rows_affected() == 0 {
+        return Err(transition_error(&state.db_pool, newsletter_issue_id).await?)?;
+    }
+
+    messages.info("The scheduled delivery has been cancelled, the issue is a draft again.");
+    Ok(Redirect::to(&format!("/admin/issues/{newsletter_issue_id}")).into_response())
+}
+
+#[tracing::instrument(
+    name = "GET - newsletter issue deliveries",
+    skip_all,
+    fields(newsletter_issue_id = %newsletter_issue_id)
+)]
+pub async fn issue_deliveries(
+    State(state): State<Arc<AppState>>,
+    Path(newsletter_issue_id): Path<Uuid>,
+    messages: Messages,
+) -> Result<Html<String>> {
+    let issue = get_issue(&state.db_pool, newsletter_issue_id)
+        .await
+        .map_err(HttpError::DatabaseError)?
+        .ok_or(HttpError::NotFound)?;
+    let summary = get_delivery_summary(&state.db_pool, newsletter_issue_id)
+        .await
+        .map_err(HttpError::DatabaseError)?;
+    let failures = get_failed_deliveries(&state.db_pool, newsletter_issue_id)
+        .await
+        .map_err(HttpError::DatabaseError)?;
+    let links = get_link_clicks(&state.db_pool, newsletter_issue_id)
+        .await
+        .map_err(HttpError::DatabaseError)?;
+
+    let mut failures_html = String::new();
+    for failure in failures {
+        let _ = writeln!(
+            failures_html,
+            "<tr><td>{email}</td><td>{n_attempts}</td><td>{last_error}</td></tr>",
+            email = htmlescape::encode_minimal(&failure.subscriber_email),
+            n_attempts = failure.n_attempts,
+            last_error =
+                htmlescape::encode_minimal(failure.last_error.as_deref().unwrap_or_default()),
+        );
+    }
+
+    let opens_html = if issue.track_opens {
+        format!(
+            r"<table>
+        <tr><th>Opened</th><td>{opened}</td></tr>
+        <tr><th>Open rate</th><td>{open_rate}</td></tr>
+        <tr><th>Total opens</th><td>{opens}</td></tr>
+    </table>",
+            opened = summary.opened,
+            open_rate = summary.open_rate(),
+            opens = summary.opens,
+        )
+    } else {
+        "<p>Opens are not tracked for this issue.</p>".to_owned()
+    };
+
+    let clicks_html = if issue.track_clicks {
+        let mut links_html = String::new();
+        for link in links {
+            let _ = writeln!(
+                links_html,
+                "<tr><td>{url}</td><td>{n_clicks}</td></tr>",
+                url = htmlescape::encode_minimal(&link.url),
+                n_clicks = link.n_clicks,
+            );
+        }
+        format!(
+            r"<table>
+        <tr><th>Clicked</th><td>{clicked}</td></tr>
+        <tr><th>Click rate</th><td>{click_rate}</td></tr>
+        <tr><th>Total clicks</th><td>{clicks}</td></tr>
+    </table>
+    <table>
+        <tr><th>Link</th><th>Clicks</th></tr>
+        {links_html}
+    </table>",
+            clicked = summary.clicked,
+            click_rate = summary.click_rate(),
+            clicks = summary.clicks,
+        )
+    } else {
+        "<p>Clicks are not tracked for this issue.</p>".to_owned()
+    };
+
+    let body = format!(
+        r"<p>{title} is {status}.</p>
+    <table>
+        <tr><th>Pending</th><td>{pending}</td></tr>
+        <tr><th>Sent</th><td>{sent}</td></tr>
+        <tr><th>Failed</th><td>{failed}</td></tr>
+        <tr><th>Skipped</th><td>{skipped}</td></tr>
+    </table>
+    <h2>Opens</h2>
+    {opens_html}
+    <h2>Clicks</h2>
+    {clicks_html}
+    <h2>Failed deliveries</h2>
+    <table>
+        <tr><th>Subscriber</th><th>Attempts</th><th>Last error</th></tr>
+        {failures_html}
+    </table>",
+        title = htmlescape::encode_minimal(&issue.title),
+        status = issue.status,
+        pending = summary.pending,
+        sent = summary.sent,
+        failed = summary.failed,
+        skipped = summary.skipped,
+    );
+
+    Ok(Html(issue_page(
+        "Deliveries",
+        &messages_html(messages),
+        &body,
+    )))
+}
+
+/// Explains why a status change matched no issue: it either does not exist or
+/// has moved on to a status the change does not apply to.
+async fn transition_error(pool: &PgPool, newsletter_issue_id: Uuid) -> Result<HttpError> {
+    let issue = get_issue(pool, newsletter_issue_id)
+        .await
+        .map_err(HttpError::DatabaseError)?;
+    Ok(match issue {
+        Some(issue) => {
+            HttpError::Conflict(format!("issue {newsletter_issue_id} is {}", issue.status))
+        }
+        None => HttpError::NotFound,
+    })
+}
+
+/// `count` as a percentage of `total`, to one decimal place.
+fn share_of(count: i64, total: i64) -> String {
+    if total == 0 {
+        return "-".to_owned();
+    }
+    let permille = count * 1000 / total;
+    format!("{}.{}%", permille / 10, permille % 10)
+}
+
+fn issue_form(
+    action: &str,
+    title: &str,
+    markdown: &str,
+    track_opens: bool,
+    track_clicks: bool,
+    submit: &str,
+) -> String {
+    let checked = |ticked: bool| if ticked { " checked" } else { "" };
+    format!(
+        r#"<form action="{action}" method="post">
+        <label>Title
+            <input type="text" placeholder="Enter the issue title" name="title" value="{title}">
+        </label>
+        <br>
+        <label>Content (Markdown)
+            <textarea placeholder="Write the issue in Markdown" name="markdown" rows="20" cols="80">{markdown}</textarea>
+        </label>
+        <br>
+        <label>
+            <input type="checkbox" name="track_opens" value="true"{opens_checked}>
+            Track opens
+        </label>
+        <br>
+        <label>
+            <input type="checkbox" name="track_clicks" value="true"{clicks_checked}>
+            Track clicks
+        </label>
+        <br>
+        <button type="submit">{submit}</button>
+    </form>"#,
+        title = htmlescape::encode_minimal(title),
+        markdown = htmlescape::encode_minimal(markdown),
+        opens_checked = checked(track_opens),
+        clicks_checked = checked(track_clicks),
+    )
+}
+
+fn schedule_form(
+    newsletter_issue_id: Uuid,
+    scheduled_for: Option<DateTime<Utc>>,
+    submit: &str,
+) -> String {
+    let value =
+        scheduled_for.map_or_else(String::new, |t| t.format("%Y-%m-%dT%H:%M:%SZ").to_string());
+    format!(
+        r#"<form action="/admin/issues/{newsletter_issue_id}/schedule" method="post">
+        <label>Send at
+            <input type="text" placeholder="2026-10-18T09:00:00Z" name="scheduled_for" value="{value}">
+        </label>
+        <button type="submit">{submit}</button>
+    </form>"#,
+    )
+}
+
+fn issue_page(page_title: &str, message_html: &str, body: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>{page_title}</title>
+</head>
+<body>
+    {message_html}
+    {body}
+    <p><a href="/admin/issues">&lt;- Back</a></p>
+</body>
+</html>"#,
+    )
+}
+
+#[tracing::instrument(name = "get newsletter issue summaries", skip_all)]
+async fn get_issue_summaries(pool: &PgPool) -> Result<Vec<IssueSummary>, sqlx::Error> {
+    sqlx::query_as!(
+        IssueSummary,
+        r#"
+        SELECT
+            newsletter_issue_id,
+            title,
+            status AS "status: NewsletterIssueStatus",
+            updated_at
+        FROM newsletter_issues
+        ORDER BY updated_at DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("execute get_issue_summaries: {e:?}");
+        e
+    })
+}
+
+#[tracing::instrument(name = "get a newsletter issue", skip_all)]
+async fn get_issue(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<Option<NewsletterIssue>, sqlx::Error> {
+    sqlx::query_as!(
+        NewsletterIssue,
+        r#"
+        SELECT
+            newsletter_issue_id,
+            title,
+            slug,
+            markdown_content,
+            html_content,
+            text_content,
+            author_id,
+            status AS "status: NewsletterIssueStatus",
+            created_at,
+            updated_at,
+            published_at,
+            scheduled_for,
+            track_opens,
+            track_clicks
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id,
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("execute get_issue: {e:?}");
+        e
+    })
+}
+
+#[tracing::instrument(name = "get a delivery summary", skip_all)]
+async fn get_delivery_summary(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<DeliverySummary, sqlx::Error> {
+    sqlx::query_as!(
+        DeliverySummary,
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE status = $2) AS "pending!",
+            COUNT(*) FILTER (WHERE status = $3) AS "sent!",
+            COUNT(*) FILTER (WHERE status = $4) AS "failed!",
+            COUNT(*) FILTER (WHERE status = $5) AS "skipped!",
+            COUNT(*) FILTER (WHERE first_opened_at IS NOT NULL) AS "opened!",
+            COALESCE(SUM(n_opens), 0) AS "opens!",
+            COUNT(*) FILTER (WHERE first_clicked_at IS NOT NULL) AS "clicked!",
+            COALESCE(SUM(n_clicks), 0) AS "clicks!"
+        FROM deliveries
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id,
+        DeliveryStatus::Pending as DeliveryStatus,
+        DeliveryStatus::Sent as DeliveryStatus,
+        DeliveryStatus::Failed as DeliveryStatus,
+        DeliveryStatus::Skipped as DeliveryStatus,
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("execute get_delivery_summary: {e:?}");
+        e
+    })
+}
+
+#[tracing::instrument(name = "get failed deliveries", skip_all)]
+async fn get_failed_deliveries(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<Vec<FailedDelivery>, sqlx::Error> {
+    sqlx::query_as!(
+        FailedDelivery,
+        r#"
+        SELECT subscriber_email, n_attempts, last_error
+        FROM deliveries
+        WHERE newsletter_issue_id = $1 AND status = $2
+        ORDER BY subscriber_email
+        "#,
+        newsletter_issue_id,
+        DeliveryStatus::Failed as DeliveryStatus,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("execute get_failed_deliveries: {e:?}");
+        e
+    })
+}
+
+#[tracing::instrument(name = "get link clicks", skip_all)]
+async fn get_link_clicks(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<Vec<LinkClicks>, sqlx::Error> {
+    sqlx::query_as!(
+        LinkClicks,
+        r#"
+        SELECT url, n_clicks
+        FROM issue_links
+        WHERE newsletter_issue_id = $1
+        ORDER BY n_clicks DESC, url
+        "#,
+        newsletter_issue_id,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("execute get_link_clicks: {e:?}");
+        e
+    })
+}
+
+#[tracing::instrument(name = "save a draft issue", skip_all)]
+async fn insert_draft(
+    pool: &PgPool,
+    author_id: Uuid,
+    title: &str,
+    markdown_content: &str,
+    content: &NewsletterContent,
+    track_opens: bool,
+    track_clicks: bool,
+) -> Result<Uuid, sqlx::Error> {
+    let newsletter_issue_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_issues (
+            newsletter_issue_id,
+            title,
+            slug,
+            markdown_content,
+            text_content,
+            html_content,
+            author_id,
+            status,
+            track_opens,
+            track_clicks
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        "#,
+        newsletter_issue_id,
+        title,
+        NewsletterIssue::slug_for(title, newsletter_issue_id),
+        markdown_content,
+        content.text,
+        content.html,
+        author_id,
+        NewsletterIssueStatus::Draft as NewsletterIssueStatus,
+        track_opens,
+        track_clicks,
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("execute insert_draft: {e:?}");
+        e
+    })?;
+    Ok(newsletter_issue_id)
+}
+
+/// Returns `false` if the issue is no longer a draft.
+#[tracing::instrument(name = "update a draft issue", skip_all)]
+async fn update_draft(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+    title: &str,
+    markdown_content: &str,
+    content: &NewsletterContent,
+    track_opens: bool,
+    track_clicks: bool,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            title = $2,
+            slug = $3,
+            markdown_content = $4,
+            text_content = $5,
+            html_content = $6,
+            track_opens = $8,
+            track_clicks = $9,
+            updated_at = NOW()
+        WHERE newsletter_issue_id = $1 AND status = $7
+        "#,
+        newsletter_issue_id,
+        title,
+        // Drafts are not in the archive yet, so their URL can still change.
+        NewsletterIssue::slug_for(title, newsletter_issue_id),
+        markdown_content,
+        content.text,
+        content.html,
+        NewsletterIssueStatus::Draft as NewsletterIssueStatus,
+        track_opens,
+        track_clicks,
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("execute update_draft: {e:?}");
+        e
+    })?;
+    Ok(result.rows_affected() == 1)
+}
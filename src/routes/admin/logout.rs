@@ -0,0 +1,12 @@
+use axum::response::Redirect;
+use axum_messages::Messages;
+
+use crate::error::Result;
+use crate::session_state::TypedSession;
+
+#[tracing::instrument(name = "POST - log out", skip_all)]
+pub async fn log_out(session: TypedSession, messages: Messages) -> Result<Redirect> {
+    session.log_out().await?;
+    messages.info("You have successfully logged out.");
+    Ok(Redirect::to("/login"))
+}
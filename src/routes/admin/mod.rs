@@ -0,0 +1,14 @@
+mod dashboard;
+mod issues;
+mod logout;
+mod password;
+mod suppressions;
+
+pub use dashboard::admin_dashboard;
+pub use issues::{
+    cancel_scheduled_issue, create_issue, edit_issue_form, issue_deliveries, list_issues,
+    new_issue_form, preview_issue, publish_issue, schedule_issue, update_issue,
+};
+pub use logout::log_out;
+pub use password::{change_password, change_password_form};
+pub use suppressions::{add_suppression, lift_suppression, list_suppressions};
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::{Extension, Form};
+use axum_messages::Messages;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+
+use super::dashboard::get_username;
+use crate::authentication::{
+    AuthError, Credentials, UserId, change_password as store_password, validate_credentials,
+};
+use crate::error::{HttpError, Result};
+use crate::routes::flash_messages::messages_html;
+use crate::startup::AppState;
+
+const MIN_PASSWORD_LENGTH: usize = 12;
+const MAX_PASSWORD_LENGTH: usize = 128;
+
+#[derive(Deserialize)]
+pub struct FormData {
+    current_password: SecretString,
+    new_password: SecretString,
+    new_password_check: SecretString,
+}
+
+pub async fn change_password_form(messages: Messages) -> Html<String> {
+    let message_html = messages_html(messages);
+
+    Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Change Password</title>
+</head>
+<body>
+    {message_html}
+    <form action="/admin/password" method="post">
+        <label>Current password
+            <input type="password" placeholder="Enter current password" name="current_password">
+        </label>
+        <br>
+        <label>New password
+            <input type="password" placeholder="Enter new password" name="new_password">
+        </label>
+        <br>
+        <label>Confirm new password
+            <input type="password" placeholder="Type the new password again" name="new_password_check">
+        </label>
+        <br>
+        <button type="submit">Change password</button>
+    </form>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+    ))
+}
+
+#[tracing::instrument(name = "POST - change password", skip_all, fields(user_id = %user_id))]
+pub async fn change_password(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    messages: Messages,
+    Form(form): Form<FormData>,
+) -> Result<Response> {
+    if form.new_password.expose_secret() != form.new_password_check.expose_secret() {
+        messages.error("You entered two different new passwords - the field values must match.");
+        return Ok(Redirect::to("/admin/password").into_response());
+    }
+
+    let new_password_length = form.new_password.expose_secret().chars().count();
+    if !(MIN_PASSWORD_LENGTH..=MAX_PASSWORD_LENGTH).contains(&new_password_length) {
+        messages.error(format!(
+            "The new password must be between {MIN_PASSWORD_LENGTH} and \
+             {MAX_PASSWORD_LENGTH} characters long."
+        ));
+        return Ok(Redirect::to("/admin/password").into_response());
+    }
+
+    let username = get_username(&state.db_pool, *user_id)
+        .await
+        .map_err(HttpError::DatabaseError)?;
+    let credentials = Credentials {
+        username,
+        password: form.current_password,
+    };
+
+    match validate_credentials(&state.db_pool, credentials).await {
+        Ok(_) => {}
+        Err(AuthError::InvalidCredentials) => {
+            messages.error("The current password is incorrect.");
+            return Ok(Redirect::to("/admin/password").into_response());
+        }
+        Err(e) => Err(HttpError::from(e))?,
+    }
+
+    store_password(&state.db_pool, *user_id, form.new_password)
+        .await
+        .map_err(HttpError::from)?;
+    messages.info("Your password has been changed.");
+    Ok(Redirect::to("/admin/password").into_response())
+}
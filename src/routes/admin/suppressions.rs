@@ -0,0 +1,167 @@
+use std::fmt::Write;
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::response::{Html, Redirect};
+use axum::{Extension, Form};
+use axum_messages::Messages;
+use serde::Deserialize;
+use sqlx::PgPool;
+use sqlx::types::chrono::{DateTime, Utc};
+
+use super::dashboard::get_username;
+use crate::authentication::UserId;
+use crate::domain::{SubscriberEmail, SuppressionReason};
+use crate::email_client::suppress_address;
+use crate::error::{HttpError, Result};
+use crate::routes::flash_messages::messages_html;
+use crate::startup::AppState;
+
+#[derive(Deserialize)]
+pub struct SuppressionFormData {
+    address: String,
+}
+
+struct Suppression {
+    address: String,
+    reason: SuppressionReason,
+    source: String,
+    created_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "GET - list suppressions", skip_all)]
+pub async fn list_suppressions(
+    State(state): State<Arc<AppState>>,
+    messages: Messages,
+) -> Result<Html<String>> {
+    let suppressions = get_suppressions(&state.db_pool)
+        .await
+        .map_err(HttpError::DatabaseError)?;
+
+    let message_html = messages_html(messages);
+
+    let mut rows_html = String::new();
+    for suppression in suppressions {
+        let address = htmlescape::encode_minimal(&suppression.address);
+        let _ = writeln!(
+            rows_html,
+            r#"<tr><td>{address}</td><td>{reason}</td><td>{source}</td><td>{created_at}</td><td><form action="/admin/suppressions/lift" method="post"><input type="hidden" name="address" value="{address}"><button type="submit">Lift</button></form></td></tr>"#,
+            reason = suppression.reason,
+            source = htmlescape::encode_minimal(&suppression.source),
+            created_at = suppression.created_at.format("%Y-%m-%d %H:%M UTC"),
+        );
+    }
+
+    Ok(Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Suppression list</title>
+</head>
+<body>
+    {message_html}
+    <p>No email is sent to these addresses.</p>
+    <form action="/admin/suppressions" method="post">
+        <label>Address
+            <input type="text" placeholder="Enter an email address" name="address">
+        </label>
+        <button type="submit">Suppress</button>
+    </form>
+    <table>
+        <tr><th>Address</th><th>Reason</th><th>Source</th><th>Since</th><th></th></tr>
+        {rows_html}
+    </table>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+    )))
+}
+
+#[tracing::instrument(name = "POST - add a suppression", skip_all, fields(user_id = %user_id))]
+pub async fn add_suppression(
+    State(state): State<Arc<AppState>>,
+    Extension(user_id): Extension<UserId>,
+    messages: Messages,
+    Form(form): Form<SuppressionFormData>,
+) -> Result<Redirect> {
+    let Ok(address) = SubscriberEmail::parse(form.address) else {
+        messages.error("That is not a valid email address.");
+        return Ok(Redirect::to("/admin/suppressions"));
+    };
+    // Report the address the way it is stored.
+    let address = address.as_ref().to_lowercase();
+    let username = get_username(&state.db_pool, *user_id)
+        .await
+        .map_err(HttpError::DatabaseError)?;
+
+    suppress_address(
+        &state.db_pool,
+        &address,
+        SuppressionReason::Manual,
+        &username,
+    )
+    .await
+    .map_err(HttpError::DatabaseError)?;
+
+    messages.info(format!("{address} will no longer be emailed."));
+    Ok(Redirect::to("/admin/suppressions"))
+}
+
+#[tracing::instrument(name = "POST - lift a suppression", skip_all)]
+pub async fn lift_suppression(
+    State(state): State<Arc<AppState>>,
+    messages: Messages,
+    Form(form): Form<SuppressionFormData>,
+) -> Result<Redirect> {
+    let lifted = delete_suppression(&state.db_pool, &form.address)
+        .await
+        .map_err(HttpError::DatabaseError)?;
+
+    if lifted {
+        messages.info(format!("{} can be emailed again.", form.address));
+    } else {
+        messages.error(format!("{} is not on the suppression list.", form.address));
+    }
+    Ok(Redirect::to("/admin/suppressions"))
+}
+
+#[tracing::instrument(name = "get suppressions", skip_all)]
+async fn get_suppressions(pool: &PgPool) -> Result<Vec<Suppression>, sqlx::Error> {
+    sqlx::query_as!(
+        Suppression,
+        r#"
+        SELECT
+            address,
+            reason AS "reason: SuppressionReason",
+            source,
+            created_at
+        FROM suppressions
+        ORDER BY created_at DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("execute get_suppressions: {e:?}");
+        e
+    })
+}
+
+#[tracing::instrument(name = "delete a suppression", skip_all)]
+async fn delete_suppression(pool: &PgPool, address: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM suppressions
+        WHERE address = LOWER($1)
+        "#,
+        address,
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("execute delete_suppression: {e:?}");
+        e
+    })?;
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,264 @@
+use std::fmt::Write;
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::header;
+use axum::response::{Html, IntoResponse, Response};
+use sqlx::PgPool;
+use sqlx::types::chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::domain::NewsletterIssueStatus;
+use crate::error::{HttpError, Result};
+use crate::startup::AppState;
+
+const FEED_TITLE: &str = "Newsletter archive";
+const FEED_LENGTH: i64 = 20;
+
+struct ArchiveEntry {
+    title: String,
+    slug: String,
+    published_at: DateTime<Utc>,
+}
+
+struct ArchivedIssue {
+    title: String,
+    from_markdown: bool,
+    html_content: String,
+    text_content: String,
+    published_at: DateTime<Utc>,
+}
+
+struct FeedEntry {
+    newsletter_issue_id: Uuid,
+    title: String,
+    slug: String,
+    from_markdown: bool,
+    html_content: String,
+    text_content: String,
+    published_at: DateTime<Utc>,
+}
+
+/// Lists every issue that has gone out, newest first. Drafts, scheduled issues
+/// and issues still being delivered stay out of the archive.
+#[tracing::instrument(name = "GET - archive", skip_all)]
+pub async fn get_archive(State(state): State<Arc<AppState>>) -> Result<Html<String>> {
+    let entries = get_archive_entries(&state.db_pool)
+        .await
+        .map_err(HttpError::DatabaseError)?;
+
+    let mut entries_html = String::new();
+    for entry in entries {
+        let _ = writeln!(
+            entries_html,
+            r#"<li><a href="/archive/{slug}">{title}</a> ({published_at})</li>"#,
+            slug = entry.slug,
+            title = htmlescape::encode_minimal(&entry.title),
+            published_at = entry.published_at.format("%Y-%m-%d"),
+        );
+    }
+
+    Ok(Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>{FEED_TITLE}</title>
+    <link rel="alternate" type="application/atom+xml" href="/feed.xml">
+</head>
+<body>
+    <h1>{FEED_TITLE}</h1>
+    <ul>
+        {entries_html}
+    </ul>
+</body>
+</html>"#,
+    )))
+}
+
+#[tracing::instrument(name = "GET - archived issue", skip_all, fields(slug = %slug))]
+pub async fn get_archived_issue(
+    State(state): State<Arc<AppState>>,
+    Path(slug): Path<String>,
+) -> Result<Html<String>> {
+    let issue = get_published_issue(&state.db_pool, &slug)
+        .await
+        .map_err(HttpError::DatabaseError)?
+        .ok_or(HttpError::NotFound)?;
+    let title = htmlescape::encode_minimal(&issue.title);
+
+    Ok(Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>{title}</title>
+</head>
+<body>
+    <h1>{title}</h1>
+    <p>Published {published_at}</p>
+    {content}
+    <p><a href="/archive">&lt;- All issues</a></p>
+</body>
+</html>"#,
+        published_at = issue.published_at.format("%Y-%m-%d"),
+        content = archived_html(
+            issue.from_markdown,
+            &issue.html_content,
+            &issue.text_content
+        ),
+    )))
+}
+
+/// An Atom feed of the most recently published issues.
+#[tracing::instrument(name = "GET - archive feed", skip_all)]
+pub async fn get_feed(State(state): State<Arc<AppState>>) -> Result<Response> {
+    let entries = get_feed_entries(&state.db_pool)
+        .await
+        .map_err(HttpError::DatabaseError)?;
+    let base_url = &state.base_url;
+    let updated = entries
+        .first()
+        .map_or_else(Utc::now, |entry| entry.published_at);
+
+    let mut entries_xml = String::new();
+    for entry in entries {
+        let _ = write!(
+            entries_xml,
+            r#"
+    <entry>
+        <title>{title}</title>
+        <link href="{base_url}/archive/{slug}"/>
+        <id>urn:uuid:{id}</id>
+        <updated>{updated}</updated>
+        <content type="html">{content}</content>
+    </entry>"#,
+            title = htmlescape::encode_minimal(&entry.title),
+            slug = entry.slug,
+            id = entry.newsletter_issue_id,
+            updated = atom_timestamp(entry.published_at),
+            content = htmlescape::encode_minimal(&archived_html(
+                entry.from_markdown,
+                &entry.html_content,
+                &entry.text_content
+            )),
+        );
+    }
+
+    let feed = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+    <title>{FEED_TITLE}</title>
+    <author><name>{FEED_TITLE}</name></author>
+    <link href="{base_url}/archive"/>
+    <link rel="self" href="{base_url}/feed.xml"/>
+    <id>{base_url}/archive</id>
+    <updated>{updated}</updated>{entries_xml}
+</feed>
+"#,
+        updated = atom_timestamp(updated),
+    );
+
+    Ok(([(header::CONTENT_TYPE, "application/atom+xml")], feed).into_response())
+}
+
+/// Issues written in Markdown keep the HTML we rendered for them. HTML posted
+/// to `POST /newsletters` is sent as is and must not be served from our origin,
+/// so those issues show their escaped plain text instead.
+fn archived_html(from_markdown: bool, html_content: &str, text_content: &str) -> String {
+    if from_markdown {
+        return html_content.to_owned();
+    }
+    let mut html = String::new();
+    for paragraph in text_content.split("\n\n").map(str::trim) {
+        if !paragraph.is_empty() {
+            let _ = writeln!(
+                html,
+                "<p>{}</p>",
+                htmlescape::encode_minimal(paragraph).replace('\n', "<br>\n")
+            );
+        }
+    }
+    html
+}
+
+fn atom_timestamp(timestamp: DateTime<Utc>) -> String {
+    timestamp.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+#[tracing::instrument(name = "get archive entries", skip_all)]
+async fn get_archive_entries(pool: &PgPool) -> Result<Vec<ArchiveEntry>, sqlx::Error> {
+    sqlx::query_as!(
+        ArchiveEntry,
+        r#"
+        SELECT title, slug, published_at AS "published_at!"
+        FROM newsletter_issues
+        WHERE status = $1
+        ORDER BY published_at DESC
+        "#,
+        NewsletterIssueStatus::Sent as NewsletterIssueStatus,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("execute get_archive_entries: {e:?}");
+        e
+    })
+}
+
+#[tracing::instrument(name = "get a published issue", skip_all)]
+async fn get_published_issue(
+    pool: &PgPool,
+    slug: &str,
+) -> Result<Option<ArchivedIssue>, sqlx::Error> {
+    sqlx::query_as!(
+        ArchivedIssue,
+        r#"
+        SELECT
+            title,
+            markdown_content IS NOT NULL AS "from_markdown!",
+            html_content,
+            text_content,
+            published_at AS "published_at!"
+        FROM newsletter_issues
+        WHERE slug = $1 AND status = $2
+        "#,
+        slug,
+        NewsletterIssueStatus::Sent as NewsletterIssueStatus,
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("execute get_published_issue: {e:?}");
+        e
+    })
+}
+
+#[tracing::instrument(name = "get feed entries", skip_all)]
+async fn get_feed_entries(pool: &PgPool) -> Result<Vec<FeedEntry>, sqlx::Error> {
+    sqlx::query_as!(
+        FeedEntry,
+        r#"
+        SELECT
+            newsletter_issue_id,
+            title,
+            slug,
+            markdown_content IS NOT NULL AS "from_markdown!",
+            html_content,
+            text_content,
+            published_at AS "published_at!"
+        FROM newsletter_issues
+        WHERE status = $1
+        ORDER BY published_at DESC
+        LIMIT $2
+        "#,
+        NewsletterIssueStatus::Sent as NewsletterIssueStatus,
+        FEED_LENGTH,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("execute get_feed_entries: {e:?}");
+        e
+    })
+}
@@ -0,0 +1,16 @@
+use std::fmt::Write;
+
+use axum_messages::Messages;
+
+/// Renders the pending flash messages as HTML, one escaped paragraph each.
+pub fn messages_html(messages: Messages) -> String {
+    let mut message_html = String::new();
+    for message in messages {
+        let _ = writeln!(
+            message_html,
+            "<p><i>{}</i></p>",
+            htmlescape::encode_minimal(&message.message)
+        );
+    }
+    message_html
+}
@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use axum::Form;
+use axum::extract::State;
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum_messages::Messages;
+use secrecy::SecretString;
+use serde::Deserialize;
+
+use crate::authentication::{AuthError, Credentials, validate_credentials};
+use crate::error::{HttpError, Result};
+use crate::routes::flash_messages::messages_html;
+use crate::session_state::TypedSession;
+use crate::startup::AppState;
+
+#[derive(Deserialize)]
+pub struct FormData {
+    username: String,
+    password: SecretString,
+}
+
+pub async fn get_login(messages: Messages) -> Html<String> {
+    let message_html = messages_html(messages);
+
+    Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Login</title>
+</head>
+<body>
+    {message_html}
+    <form action="/login" method="post">
+        <label>Username
+            <input type="text" placeholder="Enter Username" name="username">
+        </label>
+        <label>Password
+            <input type="password" placeholder="Enter Password" name="password">
+        </label>
+        <button type="submit">Login</button>
+    </form>
+</body>
+</html>"#,
+    ))
+}
+
+#[tracing::instrument(
+    name = "POST - log in",
+    skip_all,
+    fields(username = %form.username, user_id = tracing::field::Empty)
+)]
+pub async fn post_login(
+    State(state): State<Arc<AppState>>,
+    session: TypedSession,
+    messages: Messages,
+    Form(form): Form<FormData>,
+) -> Result<Response> {
+    let credentials = Credentials {
+        username: form.username,
+        password: form.password,
+    };
+
+    match validate_credentials(&state.db_pool, credentials).await {
+        Ok(user_id) => {
+            tracing::Span::current().record("user_id", tracing::field::display(&user_id));
+            session.renew().await?;
+            session.insert_user_id(user_id).await?;
+            Ok(Redirect::to("/admin/dashboard").into_response())
+        }
+        Err(e @ (AuthError::InvalidCredentials | AuthError::MissingCredentials(_))) => {
+            tracing::warn!("failed login attempt: {e}");
+            messages.error("Authentication failed");
+            Ok(Redirect::to("/login").into_response())
+        }
+        Err(e) => Err(HttpError::from(e))?,
+    }
+}
@@ -1,7 +1,27 @@
+mod admin;
+mod archive;
+mod flash_messages;
 mod health;
+mod login;
+mod newsletters;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_unsubscribe;
+mod tracking;
+mod webhooks;
 
+pub use admin::{
+    add_suppression, admin_dashboard, cancel_scheduled_issue, change_password,
+    change_password_form, create_issue, edit_issue_form, issue_deliveries, lift_suppression,
+    list_issues, list_suppressions, log_out, new_issue_form, preview_issue, publish_issue,
+    schedule_issue, update_issue,
+};
+pub use archive::{get_archive, get_archived_issue, get_feed};
 pub use health::get_health;
+pub use login::{get_login, post_login};
+pub use newsletters::post_newsletters;
 pub use subscriptions::post_subscriptions;
 pub use subscriptions_confirm::get_confirm;
+pub use subscriptions_unsubscribe::{get_unsubscribe, post_unsubscribe};
+pub use tracking::{get_click, get_open_pixel};
+pub use webhooks::post_email_webhook;
@@ -0,0 +1,161 @@
+use std::sync::Arc;
+
+use axum::Json;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::authentication::{AuthError, basic_authentication, validate_credentials};
+use crate::domain::{NewsletterContent, NewsletterIssue, NewsletterIssueStatus};
+use crate::error::{HttpError, Result};
+use crate::idempotency::{IdempotencyKey, NextAction, save_response, try_processing};
+use crate::issue_delivery_worker::enqueue_delivery_tasks;
+use crate::startup::AppState;
+
+#[derive(Deserialize, Serialize)]
+pub struct BodyData {
+    title: String,
+    content: Content,
+    /// Embed an open tracking pixel in every delivery.
+    #[serde(default)]
+    track_opens: bool,
+    /// Send every link through the click tracking redirect.
+    #[serde(default)]
+    track_clicks: bool,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Content {
+    /// Rendered to HTML and plain text when the issue is published.
+    Markdown {
+        markdown: String,
+    },
+    Html {
+        html: String,
+        text: String,
+    },
+}
+
+impl Content {
+    fn markdown(&self) -> Option<&str> {
+        match self {
+            Self::Markdown { markdown } => Some(markdown),
+            Self::Html { .. } => None,
+        }
+    }
+}
+
+impl From<Content> for NewsletterContent {
+    fn from(content: Content) -> Self {
+        match content {
+            Content::Markdown { markdown } => Self::from_markdown(&markdown),
+            Content::Html { html, text } => Self { html, text },
+        }
+    }
+}
+
+#[tracing::instrument(
+    name = "POST - publish a newsletter issue",
+    skip_all,
+    fields(username = tracing::field::Empty)
+)]
+pub async fn post_newsletters(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<BodyData>,
+) -> Result<Response> {
+    let credentials = basic_authentication(&headers).map_err(basic_auth_error)?;
+    let username = credentials.username.clone();
+    tracing::Span::current().record("username", tracing::field::display(&username));
+    let user_id = validate_credentials(&state.db_pool, credentials)
+        .await
+        .map_err(basic_auth_error)?;
+
+    let idempotency_key =
+        IdempotencyKey::from_headers(&headers).map_err(HttpError::ValidationError)?;
+    let mut transaction =
+        match try_processing(&state.db_pool, idempotency_key.as_ref(), &username, &body).await? {
+            NextAction::StartProcessing(transaction) => transaction,
+            NextAction::ReturnSavedResponse(saved_response) => return Ok(saved_response),
+        };
+
+    let markdown = body.content.markdown().map(ToOwned::to_owned);
+    let content = NewsletterContent::from(body.content);
+    let issue_id = insert_newsletter_issue(
+        &mut transaction,
+        user_id,
+        &body.title,
+        markdown.as_deref(),
+        &content,
+        body.track_opens,
+        body.track_clicks,
+    )
+    .await
+    .map_err(HttpError::DatabaseError)?;
+
+    enqueue_delivery_tasks(&mut transaction, issue_id)
+        .await
+        .map_err(HttpError::DatabaseError)?;
+
+    let response = StatusCode::ACCEPTED.into_response();
+    save_response(transaction, idempotency_key.as_ref(), &username, response).await
+}
+
+/// Rejected API clients are challenged for Basic credentials.
+fn basic_auth_error(err: AuthError) -> HttpError {
+    match HttpError::from(err) {
+        HttpError::AuthorizationError(e) => HttpError::BasicAuthError(e),
+        e => e,
+    }
+}
+
+/// Saves an issue that is published straight away, skipping the draft stage.
+#[tracing::instrument(name = "save newsletter issue details", skip_all)]
+async fn insert_newsletter_issue(
+    transaction: &mut Transaction<'_, Postgres>,
+    author_id: Uuid,
+    title: &str,
+    markdown_content: Option<&str>,
+    content: &NewsletterContent,
+    track_opens: bool,
+    track_clicks: bool,
+) -> Result<Uuid, sqlx::Error> {
+    let newsletter_issue_id = Uuid::new_v4();
+    let query = sqlx::query!(
+        r#"
+        INSERT INTO newsletter_issues (
+            newsletter_issue_id,
+            title,
+            slug,
+            markdown_content,
+            text_content,
+            html_content,
+            author_id,
+            status,
+            track_opens,
+            track_clicks,
+            published_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, NOW())
+        "#,
+        newsletter_issue_id,
+        title,
+        NewsletterIssue::slug_for(title, newsletter_issue_id),
+        markdown_content,
+        content.text,
+        content.html,
+        author_id,
+        NewsletterIssueStatus::Sending as NewsletterIssueStatus,
+        track_opens,
+        track_clicks,
+    );
+    transaction.execute(query).await.map_err(|e| {
+        tracing::error!("execute insert_newsletter_issue: {e:?}");
+        e
+    })?;
+    Ok(newsletter_issue_id)
+}
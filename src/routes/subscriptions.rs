@@ -1,20 +1,30 @@
 use std::sync::Arc;
+use std::time::Duration;
 
-use axum::response::IntoResponse;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
 use axum::{Form, extract::State};
 use rand::Rng;
 use rand::distr::Alphanumeric;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use sqlx::types::chrono::Utc;
-use sqlx::{Executor, Postgres, Transaction};
+use sqlx::{Executor, PgExecutor, Postgres, Transaction};
 use uuid::Uuid;
 
-use crate::EmailClient;
-use crate::domain::{NewSubscriber, SubscriberEmail, SubscriberName};
+use crate::domain::{NewSubscriber, SubscriberEmail, SubscriberName, SubscriptionStatus};
+use crate::email_client::lift_unsubscribe_suppression;
+use crate::email_outbox_worker::enqueue_email;
+use crate::email_templates::EmailTemplates;
 use crate::error::{HttpError, Result};
+use crate::idempotency::{
+    ANONYMOUS_USER, IdempotencyKey, NextAction, save_response, try_processing,
+};
 use crate::startup::AppState;
 
-#[derive(Deserialize)]
+/// Minimum time between two confirmation emails to the same pending subscriber.
+const CONFIRMATION_RESEND_INTERVAL: Duration = Duration::from_mins(5);
+
+#[derive(Clone, Deserialize, Serialize)]
 pub struct FormData {
     email: String,
     name: String,
@@ -27,42 +37,173 @@ pub struct FormData {
 )]
 pub async fn post_subscriptions(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Form(form): Form<FormData>,
-) -> Result<impl IntoResponse> {
-    let new_subscriber = form.try_into().map_err(HttpError::ValidationError)?;
+) -> Result<Response> {
+    let new_subscriber: NewSubscriber = form
+        .clone()
+        .try_into()
+        .map_err(HttpError::ValidationError)?;
 
-    let mut transaction = state
-        .db_pool
-        .begin()
-        .await
-        .map_err(HttpError::DatabaseError)?;
+    let idempotency_key =
+        IdempotencyKey::from_headers(&headers).map_err(HttpError::ValidationError)?;
+    let mut transaction = match try_processing(
+        &state.db_pool,
+        idempotency_key.as_ref(),
+        ANONYMOUS_USER,
+        &form,
+    )
+    .await?
+    {
+        NextAction::StartProcessing(transaction) => transaction,
+        NextAction::ReturnSavedResponse(saved_response) => return Ok(saved_response),
+    };
 
-    let subscriber_id = insert_subscriber(&mut transaction, &new_subscriber)
+    let inserted = insert_subscriber(&mut transaction, &new_subscriber)
         .await
         .map_err(HttpError::DatabaseError)?;
+    let subscriber_id = match inserted {
+        Some(subscriber_id) => Some(subscriber_id),
+        None => existing_subscriber_to_confirm(&mut transaction, &new_subscriber.email)
+            .await
+            .map_err(HttpError::DatabaseError)?,
+    };
 
-    let subscription_token = generate_subscription_token();
-    store_token(&mut transaction, subscriber_id, &subscription_token)
-        .await
-        .map_err(HttpError::DatabaseError)?;
+    if let Some(subscriber_id) = subscriber_id {
+        let subscription_token = generate_subscription_token();
+        store_token(&mut transaction, subscriber_id, &subscription_token)
+            .await
+            .map_err(HttpError::DatabaseError)?;
 
-    transaction
-        .commit()
-        .await
-        .map_err(HttpError::DatabaseError)?;
+        enqueue_confirmation_email(
+            &mut transaction,
+            &state.templates,
+            &new_subscriber,
+            &state.base_url,
+            &subscription_token,
+        )
+        .await?;
+    }
 
-    send_confirmation_email(
-        &state.email_client,
-        new_subscriber,
-        &state.base_url,
-        &subscription_token,
+    let response = StatusCode::OK.into_response();
+    save_response(
+        transaction,
+        idempotency_key.as_ref(),
+        ANONYMOUS_USER,
+        response,
     )
     .await
-    .map_err(|_| HttpError::UnexpectedError)?;
+}
+
+struct ExistingSubscriber {
+    id: Uuid,
+    status: SubscriptionStatus,
+}
+
+/// Picks the subscriber already signed up with `email` to send another
+/// confirmation email to, if any. Everyone else is answered exactly as for a
+/// new sign-up, so the form cannot be used to find out who is subscribed.
+async fn existing_subscriber_to_confirm(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &SubscriberEmail,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let ExistingSubscriber { id, status } = get_existing_subscriber(transaction, email).await?;
+    match status {
+        SubscriptionStatus::PendingConfirmation => {
+            if confirmation_recently_sent(transaction, id).await? {
+                tracing::info!("a confirmation email was sent recently, not sending another one");
+                return Ok(None);
+            }
+        }
+        // Readers who left may come back, confirming their address again.
+        SubscriptionStatus::Unsubscribed => {
+            update_subscriber_status(
+                &mut **transaction,
+                id,
+                SubscriptionStatus::PendingConfirmation,
+            )
+            .await?;
+            lift_unsubscribe_suppression(&mut **transaction, email.as_ref()).await?;
+        }
+        SubscriptionStatus::Confirmed
+        | SubscriptionStatus::Bounced
+        | SubscriptionStatus::Complained => return Ok(None),
+    }
+    revoke_tokens(transaction, id).await?;
+    Ok(Some(id))
+}
+
+/// Locks the subscriber `insert_subscriber` conflicted with until the sign-up
+/// commits.
+#[tracing::instrument(name = "get existing subscriber", skip_all)]
+async fn get_existing_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &SubscriberEmail,
+) -> Result<ExistingSubscriber, sqlx::Error> {
+    sqlx::query_as!(
+        ExistingSubscriber,
+        r#"
+        SELECT id, status AS "status: SubscriptionStatus"
+        FROM subscriptions
+        WHERE email = $1
+        FOR UPDATE
+        "#,
+        email.as_ref(),
+    )
+    .fetch_one(&mut **transaction)
+    .await
+    .map_err(|e| {
+        tracing::error!("execute get_existing_subscriber: {e:?}");
+        e
+    })
+}
+
+#[tracing::instrument(name = "check for a recent confirmation email", skip_all)]
+async fn confirmation_recently_sent(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1
+            FROM subscription_tokens
+            WHERE subscriber_id = $1 AND created_at + MAKE_INTERVAL(secs => $2) > NOW()
+        ) AS "recently_sent!"
+        "#,
+        subscriber_id,
+        CONFIRMATION_RESEND_INTERVAL.as_secs_f64(),
+    )
+    .fetch_one(&mut **transaction)
+    .await
+    .map_err(|e| {
+        tracing::error!("execute confirmation_recently_sent: {e:?}");
+        e
+    })?;
+    Ok(row.recently_sent)
+}
 
+#[tracing::instrument(name = "revoke outstanding subscription tokens", skip_all)]
+async fn revoke_tokens(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    let query = sqlx::query!(
+        r#"
+        DELETE FROM subscription_tokens
+        WHERE subscriber_id = $1 AND consumed_at IS NULL
+        "#,
+        subscriber_id,
+    );
+    transaction.execute(query).await.map_err(|e| {
+        tracing::error!("execute revoke_tokens: {e:?}");
+        e
+    })?;
     Ok(())
 }
 
+/// Returns `None` if a subscriber with the same email already exists. A
+/// concurrent sign-up with that email blocks this until it commits.
 #[tracing::instrument(
     name = "writing new subscriber to the database",
     skip_all,
@@ -71,23 +212,55 @@ pub async fn post_subscriptions(
 pub async fn insert_subscriber(
     transaction: &mut Transaction<'_, Postgres>,
     new_subscriber: &NewSubscriber,
-) -> Result<Uuid, sqlx::Error> {
-    let subscriber_id = Uuid::new_v4();
-    let query = sqlx::query!(
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let row = sqlx::query!(
         r#"
         INSERT INTO subscriptions (id, email, name, subscribed_at, status)
-        VALUES ($1, $2, $3, $4, 'pending_confirmation')
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (email) DO NOTHING
+        RETURNING id
         "#,
-        subscriber_id,
+        Uuid::new_v4(),
         new_subscriber.email.as_ref(),
         new_subscriber.name.as_ref(),
-        Utc::now()
-    );
-    transaction.execute(query).await.map_err(|e| {
+        Utc::now(),
+        SubscriptionStatus::PendingConfirmation as SubscriptionStatus,
+    )
+    .fetch_optional(&mut **transaction)
+    .await
+    .map_err(|e| {
         tracing::error!("execute insert_subscriber: {e:?}");
         e
     })?;
-    Ok(subscriber_id)
+    Ok(row.map(|row| row.id))
+}
+
+/// Moves a subscriber to `next` if their current status allows it, returning
+/// whether the subscriber was updated.
+#[tracing::instrument(name = "update subscriber status", skip_all, fields(status = ?next))]
+pub async fn update_subscriber_status(
+    executor: impl PgExecutor<'_>,
+    subscriber_id: Uuid,
+    next: SubscriptionStatus,
+) -> Result<bool, sqlx::Error> {
+    let predecessors = SubscriptionStatus::predecessors(next);
+    let result = sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET status = $2
+        WHERE id = $1 AND status = ANY($3)
+        "#,
+        subscriber_id,
+        next as SubscriptionStatus,
+        &predecessors as &[SubscriptionStatus],
+    )
+    .execute(executor)
+    .await
+    .map_err(|e| {
+        tracing::error!("execute update_subscriber_status: {e:?}");
+        e
+    })?;
+    Ok(result.rows_affected() > 0)
 }
 
 #[tracing::instrument(name = "writing subscription token to the database", skip_all)]
@@ -109,24 +282,31 @@ pub async fn store_token(
     Ok(())
 }
 
-#[tracing::instrument(name = "sending a confirmation email", skip_all)]
-pub async fn send_confirmation_email(
-    email_client: &EmailClient,
-    new_subscriber: NewSubscriber,
+/// Queues the confirmation email in the outbox, it is only sent once the
+/// sign-up transaction commits.
+#[tracing::instrument(name = "queueing a confirmation email", skip_all)]
+pub async fn enqueue_confirmation_email(
+    transaction: &mut Transaction<'_, Postgres>,
+    templates: &EmailTemplates,
+    new_subscriber: &NewSubscriber,
     base_url: &str,
     subscription_token: &str,
-) -> Result<(), reqwest::Error> {
+) -> Result<()> {
+    let subject = "Welcome!";
     let confirmation_link =
         format!("{base_url}/subscriptions/confirm?subscription_token={subscription_token}");
-    let plain_body = format!(
-        "Welcome to our newsletter!\nVisit {confirmation_link} to confirm your subscription"
-    );
-    let html_body = format!(
-        "Welcome to our newsletter!<br />Click <a href=\"{confirmation_link}\">here</a> to confirm your subscription."
-    );
-    email_client
-        .send_email(new_subscriber.email, "Welcome!", &html_body, &plain_body)
-        .await
+    let body =
+        templates.confirmation_email(subject, new_subscriber.name.as_ref(), &confirmation_link)?;
+    enqueue_email(
+        transaction,
+        &new_subscriber.email,
+        subject,
+        &body.html,
+        &body.text,
+    )
+    .await
+    .map_err(HttpError::DatabaseError)?;
+    Ok(())
 }
 
 fn generate_subscription_token() -> String {
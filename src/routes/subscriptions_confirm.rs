@@ -3,9 +3,11 @@ use std::sync::Arc;
 use axum::extract::{Query, State};
 use axum::response::IntoResponse;
 use serde::Deserialize;
-use sqlx::PgPool;
+use sqlx::{Executor, Postgres, Transaction};
 use uuid::Uuid;
 
+use super::subscriptions::update_subscriber_status;
+use crate::domain::SubscriptionStatus;
 use crate::error::{HttpError, Result};
 use crate::startup::AppState;
 
@@ -14,63 +16,112 @@ pub struct Parameters {
     subscription_token: String,
 }
 
+struct StoredToken {
+    subscriber_id: Uuid,
+    consumed: bool,
+    expired: bool,
+}
+
 #[tracing::instrument(name = "confirm a pending subscriber", skip_all)]
 pub async fn get_confirm(
     State(state): State<Arc<AppState>>,
     Query(params): Query<Parameters>,
 ) -> Result<impl IntoResponse> {
-    let id = get_subscriber_id_from_token(&state.db_pool, &params.subscription_token)
+    let mut transaction = state
+        .db_pool
+        .begin()
         .await
         .map_err(HttpError::DatabaseError)?;
+    let token = get_stored_token(
+        &mut transaction,
+        &params.subscription_token,
+        state.subscription_token_ttl,
+    )
+    .await
+    .map_err(HttpError::DatabaseError)?;
 
-    match id {
-        None => {
-            return Err(HttpError::AuthorizationError(
-                "no matching subscriber id for provided token".into(),
-            ))?;
-        }
-        Some(subscriber_id) => {
-            confirm_subscriber(&state.db_pool, subscriber_id)
-                .await
-                .map_err(HttpError::DatabaseError)?;
+    let Some(token) = token else {
+        return Err(HttpError::AuthorizationError(
+            "no matching subscriber id for provided token".into(),
+        ))?;
+    };
+    if token.consumed {
+        return Err(HttpError::Gone(
+            "subscription token has already been used".into(),
+        ))?;
+    }
+    if token.expired {
+        return Err(HttpError::Gone("subscription token has expired".into()))?;
+    }
 
-            Ok(())
-        }
+    let confirmed = update_subscriber_status(
+        &mut *transaction,
+        token.subscriber_id,
+        SubscriptionStatus::Confirmed,
+    )
+    .await
+    .map_err(HttpError::DatabaseError)?;
+    if !confirmed {
+        return Err(HttpError::Conflict(
+            "subscriber can no longer be confirmed".into(),
+        ))?;
     }
+
+    consume_token(&mut transaction, &params.subscription_token)
+        .await
+        .map_err(HttpError::DatabaseError)?;
+    transaction
+        .commit()
+        .await
+        .map_err(HttpError::DatabaseError)?;
+
+    Ok(())
 }
 
-#[tracing::instrument(name = "get subscriber id from token", skip_all)]
-pub async fn get_subscriber_id_from_token(
-    pool: &PgPool,
+#[tracing::instrument(name = "get stored subscription token", skip_all)]
+async fn get_stored_token(
+    transaction: &mut Transaction<'_, Postgres>,
     subscription_token: &str,
-) -> Result<Option<Uuid>, sqlx::Error> {
-    let result = sqlx::query!(
-        "SELECT subscriber_id FROM subscription_tokens WHERE subscription_token =
-$1",
-        subscription_token
+    ttl: std::time::Duration,
+) -> Result<Option<StoredToken>, sqlx::Error> {
+    sqlx::query_as!(
+        StoredToken,
+        r#"
+        SELECT
+            subscriber_id,
+            consumed_at IS NOT NULL AS "consumed!",
+            created_at + MAKE_INTERVAL(secs => $2) <= NOW() AS "expired!"
+        FROM subscription_tokens
+        WHERE subscription_token = $1
+        FOR UPDATE
+        "#,
+        subscription_token,
+        ttl.as_secs_f64(),
     )
-    .fetch_optional(pool)
+    .fetch_optional(&mut **transaction)
     .await
     .map_err(|e| {
         tracing::error!("failed to execute query: {e:?}");
         e
-    })?;
-    Ok(result.map(|r| r.subscriber_id))
+    })
 }
 
-#[tracing::instrument(name = "mark subscriber as confirmed", skip_all)]
-pub async fn confirm_subscriber(pool: &PgPool, subscriber_id: Uuid) -> Result<(), sqlx::Error> {
-    sqlx::query!(
-        r#"update subscriptions set status = 'confirmed' where id =
-$1"#,
-        subscriber_id
-    )
-    .execute(pool)
-    .await
-    .map_err(|e| {
-        tracing::error!("failed to execute query: {e:?}");
+#[tracing::instrument(name = "mark subscription token as consumed", skip_all)]
+async fn consume_token(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscription_token: &str,
+) -> Result<(), sqlx::Error> {
+    let query = sqlx::query!(
+        r#"
+        UPDATE subscription_tokens
+        SET consumed_at = NOW()
+        WHERE subscription_token = $1
+        "#,
+        subscription_token,
+    );
+    transaction.execute(query).await.map_err(|e| {
+        tracing::error!("execute consume_token: {e:?}");
         e
     })?;
-
     Ok(())
 }
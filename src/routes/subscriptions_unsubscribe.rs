@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use axum::Form;
+use axum::extract::{Query, State};
+use axum::response::Html;
+use serde::Deserialize;
+
+use super::subscriptions::update_subscriber_status;
+use crate::domain::{SubscriptionStatus, SuppressionReason, UnsubscribeToken};
+use crate::email_client::suppress_subscriber;
+use crate::error::{HttpError, Result};
+use crate::startup::AppState;
+
+#[derive(Deserialize)]
+pub struct Parameters {
+    token: String,
+}
+
+#[derive(Deserialize)]
+pub struct UnsubscribeFormData {
+    /// Set to `One-Click` by mail clients following RFC 8058, absent when the
+    /// reader submits the confirmation page.
+    #[serde(rename = "List-Unsubscribe")]
+    list_unsubscribe: Option<String>,
+}
+
+/// The link in every issue only asks for confirmation. Mail scanners and link
+/// prefetchers follow it too, so it must not unsubscribe anyone by itself.
+#[tracing::instrument(name = "GET - confirm unsubscribing", skip_all)]
+pub async fn get_unsubscribe(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<Parameters>,
+) -> Result<Html<String>> {
+    UnsubscribeToken::verify(&params.token, &state.hmac_secret)
+        .map_err(HttpError::ValidationError)?;
+
+    Ok(Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Unsubscribe</title>
+</head>
+<body>
+    <p>Do you want to stop receiving new issues?</p>
+    <form action="/subscriptions/unsubscribe?token={token}" method="post">
+        <button type="submit">Unsubscribe</button>
+    </form>
+</body>
+</html>"#,
+        token = htmlescape::encode_attribute(&params.token),
+    )))
+}
+
+/// Submitted from the confirmation page, or sent by mail clients on the
+/// reader's behalf as an RFC 8058 one-click unsubscribe to the URL advertised
+/// in the `List-Unsubscribe` header.
+#[tracing::instrument(name = "POST - unsubscribe a subscriber", skip_all)]
+pub async fn post_unsubscribe(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<Parameters>,
+    Form(form): Form<UnsubscribeFormData>,
+) -> Result<Html<&'static str>> {
+    let source = if form.list_unsubscribe.as_deref() == Some("One-Click") {
+        "one-click unsubscribe"
+    } else {
+        "unsubscribe link"
+    };
+    unsubscribe(&state, &params.token, source).await?;
+
+    Ok(Html(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Unsubscribed</title>
+</head>
+<body>
+    <p>You have been unsubscribed and will not receive any further issues.</p>
+</body>
+</html>"#,
+    ))
+}
+
+/// Unsubscribes the subscriber `token` was issued for and suppresses their
+/// address, `source` says how they asked.
+async fn unsubscribe(state: &AppState, token: &str, source: &str) -> Result<()> {
+    let subscriber_id =
+        UnsubscribeToken::verify(token, &state.hmac_secret).map_err(HttpError::ValidationError)?;
+
+    let mut transaction = state
+        .db_pool
+        .begin()
+        .await
+        .map_err(HttpError::DatabaseError)?;
+    let unsubscribed = update_subscriber_status(
+        &mut *transaction,
+        subscriber_id,
+        SubscriptionStatus::Unsubscribed,
+    )
+    .await
+    .map_err(HttpError::DatabaseError)?;
+    if !unsubscribed {
+        return Err(HttpError::Conflict(
+            "subscriber can no longer be unsubscribed".into(),
+        ))?;
+    }
+    suppress_subscriber(
+        &mut *transaction,
+        subscriber_id,
+        SuppressionReason::Unsubscribe,
+        source,
+    )
+    .await
+    .map_err(HttpError::DatabaseError)?;
+    transaction
+        .commit()
+        .await
+        .map_err(HttpError::DatabaseError)?;
+    Ok(())
+}
@@ -0,0 +1,172 @@
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::domain::TrackingToken;
+use crate::error::{HttpError, Result};
+use crate::startup::AppState;
+
+/// A transparent 1x1 GIF.
+const PIXEL: &[u8] = &[
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x02, 0x44, 0x01, 0x00, 0x3b,
+];
+
+/// The open tracking pixel embedded in issues that track opens, `file` is
+/// `{token}.gif`.
+///
+/// The pixel is served even if the token does not verify, a broken image in
+/// the reader's mail client would not help anyone.
+#[tracing::instrument(name = "GET - open tracking pixel", skip_all)]
+pub async fn get_open_pixel(
+    State(state): State<Arc<AppState>>,
+    Path(file): Path<String>,
+) -> Result<Response> {
+    let token = file.strip_suffix(".gif").ok_or(HttpError::NotFound)?;
+    match TrackingToken::verify(token, &state.hmac_secret) {
+        Ok((newsletter_issue_id, subscriber_id)) => {
+            let recorded = record_open(&state.db_pool, newsletter_issue_id, subscriber_id)
+                .await
+                .map_err(HttpError::DatabaseError)?;
+            if !recorded {
+                tracing::info!("no delivery matches a valid tracking token");
+            }
+        }
+        Err(e) => tracing::warn!("not recording an open: {e}"),
+    }
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "image/gif"),
+            // Every open should reach us, not a cached copy.
+            (header::CACHE_CONTROL, "no-store, max-age=0"),
+        ],
+        PIXEL,
+    )
+        .into_response())
+}
+
+/// Where tracked links in issues point. Records the click and redirects to
+/// the link's URL as recorded when the issue was published, a token can only
+/// ever lead to one of those.
+#[tracing::instrument(name = "GET - tracked link", skip_all)]
+pub async fn get_click(
+    State(state): State<Arc<AppState>>,
+    Path(token): Path<String>,
+) -> Result<Response> {
+    let (newsletter_issue_id, subscriber_id, link_id) =
+        TrackingToken::verify_link(&token, &state.hmac_secret).map_err(|e| {
+            tracing::warn!("not following a tracked link: {e}");
+            HttpError::NotFound
+        })?;
+
+    let mut transaction = state
+        .db_pool
+        .begin()
+        .await
+        .map_err(HttpError::DatabaseError)?;
+    let url = record_link_click(&mut *transaction, newsletter_issue_id, link_id)
+        .await
+        .map_err(HttpError::DatabaseError)?
+        .ok_or(HttpError::NotFound)?;
+    record_delivery_click(&mut *transaction, newsletter_issue_id, subscriber_id)
+        .await
+        .map_err(HttpError::DatabaseError)?;
+    transaction
+        .commit()
+        .await
+        .map_err(HttpError::DatabaseError)?;
+
+    Ok((StatusCode::FOUND, [(header::LOCATION, url)]).into_response())
+}
+
+#[tracing::instrument(name = "record an open", skip_all)]
+async fn record_open(
+    executor: impl PgExecutor<'_>,
+    newsletter_issue_id: Uuid,
+    subscriber_id: Uuid,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE deliveries
+        SET
+            n_opens = deliveries.n_opens + 1,
+            first_opened_at = COALESCE(deliveries.first_opened_at, NOW())
+        FROM subscriptions
+        WHERE
+            deliveries.newsletter_issue_id = $1
+            AND deliveries.subscriber_email = subscriptions.email
+            AND subscriptions.id = $2
+        "#,
+        newsletter_issue_id,
+        subscriber_id,
+    )
+    .execute(executor)
+    .await
+    .map_err(|e| {
+        tracing::error!("execute record_open: {e:?}");
+        e
+    })?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Returns the link's URL, `None` if the issue has no such link.
+#[tracing::instrument(name = "record a link click", skip_all)]
+async fn record_link_click(
+    executor: impl PgExecutor<'_>,
+    newsletter_issue_id: Uuid,
+    link_id: Uuid,
+) -> Result<Option<String>, sqlx::Error> {
+    let link = sqlx::query!(
+        r#"
+        UPDATE issue_links
+        SET n_clicks = n_clicks + 1
+        WHERE link_id = $1 AND newsletter_issue_id = $2
+        RETURNING url
+        "#,
+        link_id,
+        newsletter_issue_id,
+    )
+    .fetch_optional(executor)
+    .await
+    .map_err(|e| {
+        tracing::error!("execute record_link_click: {e:?}");
+        e
+    })?;
+    Ok(link.map(|link| link.url))
+}
+
+#[tracing::instrument(name = "record a delivery click", skip_all)]
+async fn record_delivery_click(
+    executor: impl PgExecutor<'_>,
+    newsletter_issue_id: Uuid,
+    subscriber_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE deliveries
+        SET
+            n_clicks = deliveries.n_clicks + 1,
+            first_clicked_at = COALESCE(deliveries.first_clicked_at, NOW())
+        FROM subscriptions
+        WHERE
+            deliveries.newsletter_issue_id = $1
+            AND deliveries.subscriber_email = subscriptions.email
+            AND subscriptions.id = $2
+        "#,
+        newsletter_issue_id,
+        subscriber_id,
+    )
+    .execute(executor)
+    .await
+    .map_err(|e| {
+        tracing::error!("execute record_delivery_click: {e:?}");
+        e
+    })?;
+    Ok(())
+}
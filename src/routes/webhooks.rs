@@ -0,0 +1,500 @@
+use std::sync::Arc;
+
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use sqlx::types::chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, Postgres, Transaction};
+
+use crate::authentication::basic_authentication;
+use crate::configuration::WebhookSettings;
+use crate::domain::{SubscriptionStatus, SuppressionReason};
+use crate::email_client::suppress_address;
+use crate::error::{HttpError, Result};
+use crate::startup::AppState;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// How far a Mailgun signature's timestamp may be from now, either way to
+/// allow for clock skew.
+const MAILGUN_SIGNATURE_MAX_AGE_SECONDS: i64 = 5 * 60;
+
+/// A report from an email provider that a recipient should not be mailed
+/// again.
+#[derive(Debug, PartialEq, Eq)]
+struct EmailEvent {
+    recipient: String,
+    kind: EmailEventKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EmailEventKind {
+    /// The address does not accept email.
+    Bounce,
+    /// The recipient marked one of our emails as spam.
+    Complaint,
+}
+
+impl EmailEventKind {
+    /// The status the recipient's subscription moves to.
+    const fn subscription_status(self) -> SubscriptionStatus {
+        match self {
+            Self::Bounce => SubscriptionStatus::Bounced,
+            Self::Complaint => SubscriptionStatus::Complained,
+        }
+    }
+
+    const fn suppression_reason(self) -> SuppressionReason {
+        match self {
+            Self::Bounce => SuppressionReason::Bounce,
+            Self::Complaint => SuppressionReason::Complaint,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "RecordType", rename_all_fields = "PascalCase")]
+enum PostmarkEvent {
+    Bounce {
+        email: String,
+        /// Set once Postmark has stopped sending to the address, i.e. for
+        /// hard bounces but not for soft ones.
+        inactive: bool,
+    },
+    SpamComplaint {
+        email: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct MailgunWebhook {
+    signature: MailgunSignature,
+    #[serde(rename = "event-data")]
+    event_data: MailgunEventData,
+}
+
+#[derive(Deserialize)]
+struct MailgunSignature {
+    timestamp: String,
+    token: String,
+    signature: String,
+}
+
+#[derive(Deserialize)]
+struct MailgunEventData {
+    event: String,
+    /// `permanent` or `temporary`, only present on `failed` events.
+    severity: Option<String>,
+    recipient: String,
+}
+
+/// Bounce and spam complaint notifications pushed by the email provider. The
+/// affected address is suppressed and its subscriber stops receiving issues.
+///
+/// Events we do not act on are still acknowledged, so the provider does not
+/// keep retrying them.
+#[tracing::instrument(name = "POST - email webhook", skip_all, fields(provider = %provider))]
+pub async fn post_email_webhook(
+    State(state): State<Arc<AppState>>,
+    Path(provider): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode> {
+    let mut transaction = state
+        .db_pool
+        .begin()
+        .await
+        .map_err(HttpError::DatabaseError)?;
+    let event = match provider.as_str() {
+        "postmark" => postmark_event(&state.webhooks, &headers, &body)?,
+        "mailgun" => mailgun_event(&mut transaction, &state.webhooks, &body).await?,
+        _ => return Err(HttpError::NotFound)?,
+    };
+    let Some(event) = event else {
+        transaction
+            .commit()
+            .await
+            .map_err(HttpError::DatabaseError)?;
+        return Ok(StatusCode::OK);
+    };
+
+    let status = event.kind.subscription_status();
+    suppress_address(
+        &mut *transaction,
+        &event.recipient,
+        event.kind.suppression_reason(),
+        &provider,
+    )
+    .await
+    .map_err(HttpError::DatabaseError)?;
+    let updated = update_subscriber_status_by_email(&mut *transaction, &event.recipient, status)
+        .await
+        .map_err(HttpError::DatabaseError)?;
+    transaction
+        .commit()
+        .await
+        .map_err(HttpError::DatabaseError)?;
+    if updated {
+        tracing::info!("moved a subscriber to {status:?}");
+    } else {
+        tracing::info!("no subscription could be moved to {status:?}");
+    }
+    Ok(StatusCode::OK)
+}
+
+/// Postmark authenticates webhooks with the basic auth credentials set on the
+/// webhook URL.
+fn postmark_event(
+    settings: &WebhookSettings,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<Option<EmailEvent>, HttpError> {
+    let credentials = basic_authentication(headers).map_err(HttpError::from)?;
+    let authenticated = secrets_match(&credentials.username, &settings.postmark_username)
+        && secrets_match(
+            credentials.password.expose_secret(),
+            settings.postmark_password.expose_secret(),
+        );
+    if !authenticated {
+        return Err(HttpError::AuthorizationError(
+            "invalid postmark webhook credentials".into(),
+        ));
+    }
+
+    let event: PostmarkEvent =
+        serde_json::from_slice(body).map_err(|e| HttpError::ValidationError(e.to_string()))?;
+    Ok(parse_postmark_event(event))
+}
+
+fn parse_postmark_event(event: PostmarkEvent) -> Option<EmailEvent> {
+    match event {
+        PostmarkEvent::Bounce {
+            email,
+            inactive: true,
+        } => Some(EmailEvent {
+            recipient: email,
+            kind: EmailEventKind::Bounce,
+        }),
+        PostmarkEvent::SpamComplaint { email } => Some(EmailEvent {
+            recipient: email,
+            kind: EmailEventKind::Complaint,
+        }),
+        PostmarkEvent::Bounce { .. } | PostmarkEvent::Other => None,
+    }
+}
+
+/// Mailgun signs every webhook payload with the account's signing key. Each
+/// signature is only accepted once and for a few minutes, so a captured
+/// payload cannot be replayed.
+async fn mailgun_event(
+    transaction: &mut Transaction<'_, Postgres>,
+    settings: &WebhookSettings,
+    body: &[u8],
+) -> Result<Option<EmailEvent>, HttpError> {
+    let webhook: MailgunWebhook =
+        serde_json::from_slice(body).map_err(|e| HttpError::ValidationError(e.to_string()))?;
+    verify_mailgun_signature(&settings.mailgun_signing_key, &webhook.signature)
+        .map_err(HttpError::AuthorizationError)?;
+    let expires_at = mailgun_signature_expiry(&webhook.signature.timestamp, Utc::now())
+        .map_err(HttpError::AuthorizationError)?;
+    let first_use = record_mailgun_token(transaction, &webhook.signature.token, expires_at)
+        .await
+        .map_err(HttpError::DatabaseError)?;
+    if !first_use {
+        return Err(HttpError::AuthorizationError(
+            "mailgun webhook token was already used".into(),
+        ));
+    }
+    Ok(parse_mailgun_event(webhook.event_data))
+}
+
+/// When a signature made at `timestamp`, in seconds since the epoch, stops
+/// being accepted, or why it is not accepted now.
+fn mailgun_signature_expiry(timestamp: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let timestamp: i64 = timestamp
+        .parse()
+        .map_err(|_| "mailgun timestamp is not a number".to_owned())?;
+    if (now.timestamp() - timestamp).abs() > MAILGUN_SIGNATURE_MAX_AGE_SECONDS {
+        return Err("mailgun signature is too old".into());
+    }
+    DateTime::from_timestamp(timestamp + MAILGUN_SIGNATURE_MAX_AGE_SECONDS, 0)
+        .ok_or_else(|| "mailgun timestamp is out of range".to_owned())
+}
+
+fn verify_mailgun_signature(
+    signing_key: &SecretString,
+    signature: &MailgunSignature,
+) -> Result<(), String> {
+    let tag = decode_hex(&signature.signature)
+        .ok_or_else(|| "mailgun signature is not hex".to_owned())?;
+    let mut mac = HmacSha256::new_from_slice(signing_key.expose_secret().as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(signature.timestamp.as_bytes());
+    mac.update(signature.token.as_bytes());
+    mac.verify_slice(&tag)
+        .map_err(|_| "mailgun signature is invalid".to_owned())
+}
+
+fn parse_mailgun_event(event_data: MailgunEventData) -> Option<EmailEvent> {
+    let kind = match (event_data.event.as_str(), event_data.severity.as_deref()) {
+        ("failed", Some("permanent")) => EmailEventKind::Bounce,
+        ("complained", _) => EmailEventKind::Complaint,
+        _ => return None,
+    };
+    Some(EmailEvent {
+        recipient: event_data.recipient,
+        kind,
+    })
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.is_ascii() || !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Compares digests rather than the secrets themselves, so the time taken
+/// does not reveal how much of a guess was right.
+fn secrets_match(candidate: &str, expected: &str) -> bool {
+    Sha256::digest(candidate) == Sha256::digest(expected)
+}
+
+/// Remembers a Mailgun webhook token until it expires, returning whether it
+/// was seen for the first time.
+#[tracing::instrument(name = "record a mailgun webhook token", skip_all)]
+async fn record_mailgun_token(
+    transaction: &mut Transaction<'_, Postgres>,
+    token: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query!("DELETE FROM mailgun_webhook_tokens WHERE expires_at < NOW()")
+        .execute(&mut **transaction)
+        .await
+        .map_err(|e| {
+            tracing::error!("execute record_mailgun_token: {e:?}");
+            e
+        })?;
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO mailgun_webhook_tokens (token, expires_at)
+        VALUES ($1, $2)
+        ON CONFLICT (token) DO NOTHING
+        "#,
+        token,
+        expires_at,
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|e| {
+        tracing::error!("execute record_mailgun_token: {e:?}");
+        e
+    })?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[tracing::instrument(name = "update a subscriber's status by email", skip_all)]
+async fn update_subscriber_status_by_email(
+    executor: impl PgExecutor<'_>,
+    email: &str,
+    next: SubscriptionStatus,
+) -> Result<bool, sqlx::Error> {
+    let predecessors = SubscriptionStatus::predecessors(next);
+    let result = sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET status = $2
+        WHERE email = $1 AND status = ANY($3)
+        "#,
+        email,
+        next as SubscriptionStatus,
+        &predecessors as &[SubscriptionStatus],
+    )
+    .execute(executor)
+    .await
+    .map_err(|e| {
+        tracing::error!("execute update_subscriber_status_by_email: {e:?}");
+        e
+    })?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write;
+
+    use claims::{assert_err, assert_ok, assert_some_eq};
+
+    use super::*;
+
+    fn postmark(body: &serde_json::Value) -> Option<EmailEvent> {
+        let event = serde_json::from_value(body.clone()).expect("failed to parse postmark event");
+        parse_postmark_event(event)
+    }
+
+    fn signing_key() -> SecretString {
+        SecretString::from("mailgun-signing-key")
+    }
+
+    fn mailgun_signature(timestamp: &str, token: &str) -> String {
+        let mut mac =
+            HmacSha256::new_from_slice(b"mailgun-signing-key").expect("HMAC accepts any key");
+        mac.update(timestamp.as_bytes());
+        mac.update(token.as_bytes());
+        mac.finalize()
+            .into_bytes()
+            .iter()
+            .fold(String::new(), |mut hex, byte| {
+                let _ = write!(hex, "{byte:02x}");
+                hex
+            })
+    }
+
+    #[test]
+    fn postmark_hard_bounces_bounce_the_recipient() {
+        let event = postmark(&serde_json::json!({
+            "RecordType": "Bounce",
+            "Type": "HardBounce",
+            "Email": "ursula@example.com",
+            "Inactive": true,
+        }));
+
+        assert_some_eq!(
+            event,
+            EmailEvent {
+                recipient: "ursula@example.com".into(),
+                kind: EmailEventKind::Bounce,
+            }
+        );
+    }
+
+    #[test]
+    fn postmark_soft_bounces_are_ignored() {
+        let event = postmark(&serde_json::json!({
+            "RecordType": "Bounce",
+            "Type": "SoftBounce",
+            "Email": "ursula@example.com",
+            "Inactive": false,
+        }));
+
+        assert_eq!(event, None);
+    }
+
+    #[test]
+    fn postmark_spam_complaints_mark_the_recipient_as_complained() {
+        let event = postmark(&serde_json::json!({
+            "RecordType": "SpamComplaint",
+            "Email": "ursula@example.com",
+        }));
+
+        assert_some_eq!(
+            event,
+            EmailEvent {
+                recipient: "ursula@example.com".into(),
+                kind: EmailEventKind::Complaint,
+            }
+        );
+    }
+
+    #[test]
+    fn other_postmark_events_are_ignored() {
+        let event = postmark(&serde_json::json!({
+            "RecordType": "Delivery",
+            "Recipient": "ursula@example.com",
+        }));
+
+        assert_eq!(event, None);
+    }
+
+    #[test]
+    fn mailgun_temporary_failures_are_ignored() {
+        let event = parse_mailgun_event(MailgunEventData {
+            event: "failed".into(),
+            severity: Some("temporary".into()),
+            recipient: "ursula@example.com".into(),
+        });
+
+        assert_eq!(event, None);
+    }
+
+    #[test]
+    fn a_correctly_signed_mailgun_payload_verifies() {
+        let signature = MailgunSignature {
+            timestamp: "1529006854".into(),
+            token: "a8ce0edb2dd8301dee6c2405235584e45aa91d1e9f979f3de0".into(),
+            signature: mailgun_signature(
+                "1529006854",
+                "a8ce0edb2dd8301dee6c2405235584e45aa91d1e9f979f3de0",
+            ),
+        };
+
+        assert_ok!(verify_mailgun_signature(&signing_key(), &signature));
+    }
+
+    #[test]
+    fn a_mailgun_payload_with_a_tampered_timestamp_is_rejected() {
+        let signature = MailgunSignature {
+            timestamp: "1529006855".into(),
+            token: "a8ce0edb2dd8301dee6c2405235584e45aa91d1e9f979f3de0".into(),
+            signature: mailgun_signature(
+                "1529006854",
+                "a8ce0edb2dd8301dee6c2405235584e45aa91d1e9f979f3de0",
+            ),
+        };
+
+        assert_err!(verify_mailgun_signature(&signing_key(), &signature));
+    }
+
+    #[test]
+    fn a_mailgun_signature_that_is_not_hex_is_rejected() {
+        let signature = MailgunSignature {
+            timestamp: "1529006854".into(),
+            token: "token".into(),
+            signature: "é".repeat(32),
+        };
+
+        assert_err!(verify_mailgun_signature(&signing_key(), &signature));
+    }
+
+    fn at(timestamp: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(timestamp, 0).expect("timestamp out of range")
+    }
+
+    #[test]
+    fn a_recent_mailgun_signature_expires_a_few_minutes_after_it_was_made() {
+        let expires_at = mailgun_signature_expiry("1529006854", at(1_529_006_854 + 60));
+
+        assert_eq!(
+            assert_ok!(expires_at),
+            at(1_529_006_854 + MAILGUN_SIGNATURE_MAX_AGE_SECONDS)
+        );
+    }
+
+    #[test]
+    fn an_old_mailgun_signature_is_rejected() {
+        let now = at(1_529_006_854 + MAILGUN_SIGNATURE_MAX_AGE_SECONDS + 1);
+
+        assert_err!(mailgun_signature_expiry("1529006854", now));
+    }
+
+    #[test]
+    fn a_mailgun_signature_from_the_future_is_rejected() {
+        let now = at(1_529_006_854 - MAILGUN_SIGNATURE_MAX_AGE_SECONDS - 1);
+
+        assert_err!(mailgun_signature_expiry("1529006854", now));
+    }
+
+    #[test]
+    fn a_mailgun_timestamp_that_is_not_a_number_is_rejected() {
+        assert_err!(mailgun_signature_expiry("yesterday", at(1_529_006_854)));
+    }
+}
@@ -0,0 +1,104 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+use tower_sessions::SessionStore;
+use tower_sessions::cookie::time::OffsetDateTime;
+use tower_sessions::session::{Id, Record};
+use tower_sessions::session_store::{Error, Result};
+
+/// Keeps admin sessions in the `sessions` table, so logins survive restarts and
+/// are shared between instances.
+///
+/// Only the session data goes into the JSONB column, the id is an `i128` that
+/// `serde_json::Value` cannot hold.
+#[derive(Clone, Debug)]
+pub struct PgSessionStore {
+    pool: PgPool,
+}
+
+impl PgSessionStore {
+    pub const fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl SessionStore for PgSessionStore {
+    async fn create(&self, record: &mut Record) -> Result<()> {
+        loop {
+            let data =
+                serde_json::to_value(&record.data).map_err(|e| Error::Encode(e.to_string()))?;
+            let n_inserted_rows = sqlx::query!(
+                r#"
+                INSERT INTO sessions (id, data, expiry_date)
+                VALUES ($1, $2, TO_TIMESTAMP($3::BIGINT))
+                ON CONFLICT DO NOTHING
+                "#,
+                record.id.to_string(),
+                data,
+                record.expiry_date.unix_timestamp(),
+            )
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::Backend(e.to_string()))?
+            .rows_affected();
+
+            if n_inserted_rows > 0 {
+                return Ok(());
+            }
+            record.id = Id::default();
+        }
+    }
+
+    async fn save(&self, record: &Record) -> Result<()> {
+        let data = serde_json::to_value(&record.data).map_err(|e| Error::Encode(e.to_string()))?;
+        sqlx::query!(
+            r#"
+            INSERT INTO sessions (id, data, expiry_date)
+            VALUES ($1, $2, TO_TIMESTAMP($3::BIGINT))
+            ON CONFLICT (id) DO UPDATE
+            SET data = EXCLUDED.data, expiry_date = EXCLUDED.expiry_date
+            "#,
+            record.id.to_string(),
+            data,
+            record.expiry_date.unix_timestamp(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::Backend(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn load(&self, session_id: &Id) -> Result<Option<Record>> {
+        let row = sqlx::query!(
+            r#"
+            SELECT data, EXTRACT(EPOCH FROM expiry_date)::BIGINT AS "expiry_date!"
+            FROM sessions
+            WHERE id = $1 AND expiry_date > NOW()
+            "#,
+            session_id.to_string(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| Error::Backend(e.to_string()))?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let data = serde_json::from_value(row.data).map_err(|e| Error::Decode(e.to_string()))?;
+        let expiry_date = OffsetDateTime::from_unix_timestamp(row.expiry_date)
+            .map_err(|e| Error::Decode(e.to_string()))?;
+        Ok(Some(Record {
+            id: *session_id,
+            data,
+            expiry_date,
+        }))
+    }
+
+    async fn delete(&self, session_id: &Id) -> Result<()> {
+        sqlx::query!("DELETE FROM sessions WHERE id = $1", session_id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::Backend(e.to_string()))?;
+        Ok(())
+    }
+}
@@ -4,24 +4,44 @@ use std::sync::Arc;
 
 use axum::Router;
 // use axum::http::Request;
+use axum::middleware;
 use axum::routing::{get, post};
+use axum_messages::MessagesManagerLayer;
+use secrecy::SecretString;
 use sqlx::PgPool;
 use sqlx::postgres::PgPoolOptions;
+use tower_sessions::cookie::time::Duration;
+use tower_sessions::{Expiry, SessionManagerLayer};
 // use tower::ServiceBuilder;
 // use tower_http::ServiceBuilderExt;
 // use tower_http::request_id::{MakeRequestId, RequestId};
 // use tower_http::trace::{DefaultMakeSpan, DefaultOnResponse, TraceLayer};
 // use uuid::Uuid;
 
-use crate::configuration::{DatabaseSettings, Settings};
-use crate::routes::{get_confirm, get_health, post_subscriptions};
-use crate::{EmailClient, telemetry::tracing_layer};
+use crate::authentication::{reject_anonymous_users, seed_admin};
+use crate::configuration::{DatabaseSettings, Settings, WebhookSettings};
+use crate::email_templates::EmailTemplates;
+use crate::routes::{
+    add_suppression, admin_dashboard, cancel_scheduled_issue, change_password,
+    change_password_form, create_issue, edit_issue_form, get_archive, get_archived_issue,
+    get_click, get_confirm, get_feed, get_health, get_login, get_open_pixel, get_unsubscribe,
+    issue_deliveries, lift_suppression, list_issues, list_suppressions, log_out, new_issue_form,
+    post_email_webhook, post_login, post_newsletters, post_subscriptions, post_unsubscribe,
+    preview_issue, publish_issue, schedule_issue, update_issue,
+};
+use crate::session_store::PgSessionStore;
+use crate::telemetry::tracing_layer;
+
+const SESSION_INACTIVITY_TIMEOUT: Duration = Duration::hours(1);
 
 #[derive(Debug)]
 pub struct AppState {
     pub db_pool: PgPool,
-    pub email_client: EmailClient,
+    pub templates: EmailTemplates,
     pub base_url: String,
+    pub hmac_secret: SecretString,
+    pub subscription_token_ttl: std::time::Duration,
+    pub webhooks: WebhookSettings,
 }
 
 #[derive(Debug)]
@@ -32,25 +52,27 @@ pub struct Application {
 }
 
 impl Application {
-    pub fn build(configuration: Settings) -> io::Result<Self> {
+    pub async fn build(configuration: Settings) -> io::Result<Self> {
         let db_pool = get_connection_pool(&configuration.database);
-
-        let sender_email = configuration
-            .email_client
-            .sender()
-            .expect("invalid sender email address");
-        let timeout = configuration.email_client.timeout();
-        let email_client = EmailClient::new(
-            configuration.email_client.base_url,
-            sender_email,
-            configuration.email_client.api_token,
-            timeout,
-        );
-
+        seed_admin(&db_pool, &configuration.admin)
+            .await
+            .map_err(io::Error::other)?;
+        let templates = EmailTemplates::load(&configuration.application.templates_directory)
+            .map_err(io::Error::other)?;
+
+        let session_store = PgSessionStore::new(db_pool.clone());
+        let session_layer = SessionManagerLayer::new(session_store)
+            .with_secure(configuration.application.secure_cookies)
+            .with_expiry(Expiry::OnInactivity(SESSION_INACTIVITY_TIMEOUT));
+
+        let subscription_token_ttl = configuration.application.subscription_token_ttl();
         let shared_state = Arc::new(AppState {
             db_pool,
-            email_client,
+            templates,
             base_url: configuration.application.base_url,
+            hmac_secret: configuration.application.hmac_secret,
+            subscription_token_ttl,
+            webhooks: configuration.webhooks,
         });
 
         // let svc = ServiceBuilder::new()
@@ -62,12 +84,46 @@ impl Application {
         //     )
         //     .propagate_x_request_id();
 
+        let admin_routes = Router::new()
+            .route("/dashboard", get(admin_dashboard))
+            .route("/issues", get(list_issues).post(create_issue))
+            .route("/issues/new", get(new_issue_form))
+            .route("/issues/{id}", get(edit_issue_form).post(update_issue))
+            .route("/issues/{id}/preview", get(preview_issue))
+            .route("/issues/{id}/publish", post(publish_issue))
+            .route("/issues/{id}/schedule", post(schedule_issue))
+            .route("/issues/{id}/cancel", post(cancel_scheduled_issue))
+            .route("/issues/{id}/deliveries", get(issue_deliveries))
+            .route(
+                "/suppressions",
+                get(list_suppressions).post(add_suppression),
+            )
+            .route("/suppressions/lift", post(lift_suppression))
+            .route("/password", get(change_password_form).post(change_password))
+            .route("/logout", post(log_out))
+            .layer(middleware::from_fn(reject_anonymous_users));
+
         let mut router = Router::new()
             .route("/health", get(get_health))
+            .route("/archive", get(get_archive))
+            .route("/archive/{slug}", get(get_archived_issue))
+            .route("/feed.xml", get(get_feed))
+            .route("/login", get(get_login).post(post_login))
+            .route("/newsletters", post(post_newsletters))
             .route("/subscriptions", post(post_subscriptions))
             .route("/subscriptions/confirm", get(get_confirm))
+            .route(
+                "/subscriptions/unsubscribe",
+                get(get_unsubscribe).post(post_unsubscribe),
+            )
+            .route("/webhooks/email/{provider}", post(post_email_webhook))
+            .route("/t/o/{file}", get(get_open_pixel))
+            .route("/t/c/{token}", get(get_click))
+            .nest("/admin", admin_routes)
             // .layer(svc)
-            .with_state(shared_state);
+            .with_state(shared_state)
+            .layer(MessagesManagerLayer)
+            .layer(session_layer);
 
         router = tracing_layer(router);
 
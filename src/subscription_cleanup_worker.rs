@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use sqlx::{Executor, PgPool};
+
+use crate::configuration::Settings;
+use crate::domain::SubscriptionStatus;
+use crate::startup::get_connection_pool;
+
+const CLEANUP_INTERVAL: Duration = Duration::from_hours(1);
+
+pub async fn run_cleanup_until_stopped(configuration: Settings) -> Result<(), std::io::Error> {
+    let db_pool = get_connection_pool(&configuration.database);
+    let ttl = configuration.application.subscription_token_ttl();
+    loop {
+        // Failures are already logged, the next run will pick up where this one left off.
+        let _ = purge_stale_subscriptions(&db_pool, ttl).await;
+        tokio::time::sleep(CLEANUP_INTERVAL).await;
+    }
+}
+
+/// Deletes subscription tokens that were used or are older than `ttl`, then
+/// subscribers who never confirmed and no longer hold a usable token.
+#[tracing::instrument(name = "purge stale subscriptions", skip_all, err)]
+pub async fn purge_stale_subscriptions(pool: &PgPool, ttl: Duration) -> Result<(), sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+
+    let query = sqlx::query!(
+        r#"
+        DELETE FROM subscription_tokens
+        WHERE consumed_at IS NOT NULL OR created_at + MAKE_INTERVAL(secs => $1) <= NOW()
+        "#,
+        ttl.as_secs_f64(),
+    );
+    let n_tokens = transaction.execute(query).await?.rows_affected();
+
+    let query = sqlx::query!(
+        r#"
+        DELETE FROM subscriptions
+        WHERE
+            status = $1
+            AND subscribed_at + MAKE_INTERVAL(secs => $2) <= NOW()
+            AND NOT EXISTS (
+                SELECT 1
+                FROM subscription_tokens
+                WHERE subscription_tokens.subscriber_id = subscriptions.id
+            )
+        "#,
+        SubscriptionStatus::PendingConfirmation as SubscriptionStatus,
+        ttl.as_secs_f64(),
+    );
+    let n_subscribers = transaction.execute(query).await?.rows_affected();
+
+    transaction.commit().await?;
+    tracing::info!("purged {n_tokens} stale tokens and {n_subscribers} unconfirmed subscribers");
+    Ok(())
+}
@@ -6,6 +6,7 @@ use axum::{Router, body::Body, extract::ConnectInfo};
 use opentelemetry::trace::TracerProvider as _;
 use opentelemetry_otlp::{Protocol, WithExportConfig};
 use opentelemetry_sdk::trace::SdkTracerProvider;
+use tokio::task::JoinHandle;
 use tower_http::trace::TraceLayer;
 use tracing::{Span, Subscriber, subscriber::set_global_default};
 use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
@@ -47,6 +48,15 @@ pub fn init_subscriber(subscriber: impl Subscriber + Send + Sync) {
     set_global_default(subscriber).expect("Failed to set subscriber");
 }
 
+pub fn spawn_blocking_with_tracing<F, R>(f: F) -> JoinHandle<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let current_span = Span::current();
+    tokio::task::spawn_blocking(move || current_span.in_scope(f))
+}
+
 pub fn tracing_layer(router: Router) -> Router {
     router.layer(
         TraceLayer::new_for_http()
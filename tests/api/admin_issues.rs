@@ -0,0 +1,166 @@
+use anyhow::Result;
+use reqwest::StatusCode;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{AcceptBatch, assert_is_redirect_to, create_confirmed_subscriber, spawn_app};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_manage_issues() -> Result<()> {
+    let app = spawn_app().await?;
+
+    let response = app
+        .post_admin_issue(&serde_json::json!({
+            "title": "Issue #1",
+            "markdown": "Some news.",
+        }))
+        .await?;
+
+    assert_is_redirect_to(&response, "/login");
+    Ok(())
+}
+
+#[tokio::test]
+async fn drafts_are_listed_and_not_sent() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    app.login().await?;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let issue_path = app.create_draft_issue().await?;
+    app.dispatch_all_pending_emails().await?;
+
+    let html_page = app.get_admin_issue(&issue_path).await?.text().await?;
+    assert!(html_page.contains("<p><i>Your draft has been saved.</i></p>"));
+    let html_page = app.get_admin_issues_html().await?;
+    assert!(html_page.contains("Issue #1"));
+    assert!(html_page.contains("draft"));
+    assert_eq!(app.issue_status().await?, "draft");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn drafts_need_a_title() -> Result<()> {
+    let app = spawn_app().await?;
+    app.login().await?;
+
+    let response = app
+        .post_admin_issue(&serde_json::json!({
+            "title": " ",
+            "markdown": "Some news.",
+        }))
+        .await?;
+
+    assert_is_redirect_to(&response, "/admin/issues/new");
+    let html_page = app
+        .get_admin_issue("/admin/issues/new")
+        .await?
+        .text()
+        .await?;
+    assert!(html_page.contains("<p><i>The issue needs a title.</i></p>"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn drafts_can_be_edited_and_previewed() -> Result<()> {
+    let app = spawn_app().await?;
+    app.login().await?;
+    let issue_path = app.create_draft_issue().await?;
+
+    let response = app
+        .post_admin_issue_update(
+            &issue_path,
+            &serde_json::json!({
+                "title": "Issue #1, revised",
+                "markdown": "Some **better** news.",
+            }),
+        )
+        .await?;
+    assert_is_redirect_to(&response, &issue_path);
+
+    let html_page = app.get_admin_issue(&issue_path).await?.text().await?;
+    assert!(html_page.contains("Issue #1, revised"));
+    assert!(html_page.contains("Some **better** news."));
+
+    let preview = app.get_admin_issue_preview_html(&issue_path).await?;
+    assert!(preview.contains("<strong>better</strong>"));
+    assert!(preview.contains(&format!("Hi {}", app.test_user.username)));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unknown_issues_are_not_found() -> Result<()> {
+    let app = spawn_app().await?;
+    app.login().await?;
+
+    let response = app
+        .get_admin_issue(&format!("/admin/issues/{}", uuid::Uuid::new_v4()))
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+#[tokio::test]
+async fn publishing_a_draft_delivers_it_to_confirmed_subscribers() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    app.login().await?;
+    let issue_path = app.create_draft_issue().await?;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(AcceptBatch)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_publish_issue(&issue_path).await?;
+    assert_is_redirect_to(&response, "/admin/issues");
+    assert_eq!(app.issue_status().await?, "sending");
+
+    app.dispatch_all_pending_emails().await?;
+    assert_eq!(app.issue_status().await?, "sent");
+
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_requests.last().unwrap().body)?;
+    assert!(
+        body[0]["HtmlBody"]
+            .as_str()
+            .unwrap()
+            .contains("<em>news</em>")
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn published_issues_can_no_longer_be_edited_or_published() -> Result<()> {
+    let app = spawn_app().await?;
+    app.login().await?;
+    let issue_path = app.create_draft_issue().await?;
+    app.post_publish_issue(&issue_path).await?;
+
+    let response = app
+        .post_admin_issue_update(
+            &issue_path,
+            &serde_json::json!({
+                "title": "Issue #1, revised",
+                "markdown": "Too late.",
+            }),
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = app.post_publish_issue(&issue_path).await?;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    Ok(())
+}
@@ -0,0 +1,156 @@
+use anyhow::Result;
+use reqwest::StatusCode;
+
+use wiremock::Mock;
+use wiremock::matchers::path;
+
+use crate::helpers::{AcceptBatch, TestApp, create_confirmed_subscriber, spawn_app};
+
+async fn publish_issue(app: &TestApp) -> Result<()> {
+    let response = app
+        .post_newsletters(&serde_json::json!({
+            "title": "Issue #1: <Hello>",
+            "content": {
+                "markdown": "Newsletter body as **Markdown**",
+            }
+        }))
+        .await?;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    Ok(())
+}
+
+async fn issue_slug(app: &TestApp) -> Result<String> {
+    let row = sqlx::query!("SELECT slug FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await?;
+    Ok(row.slug)
+}
+
+#[tokio::test]
+async fn published_issues_are_listed_in_the_archive() -> Result<()> {
+    let app = spawn_app().await?;
+    publish_issue(&app).await?;
+    let slug = issue_slug(&app).await?;
+    assert!(slug.starts_with("issue-1-hello-"));
+
+    let html_page = app.get_archive_html().await?;
+
+    assert!(html_page.contains("Issue #1: &lt;Hello&gt;"));
+    assert!(html_page.contains(&format!(r#"href="/archive/{slug}""#)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn archived_issues_are_rendered_as_pages() -> Result<()> {
+    let app = spawn_app().await?;
+    publish_issue(&app).await?;
+    let slug = issue_slug(&app).await?;
+
+    let response = app.get_archived_issue(&slug).await?;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let html_page = response.text().await?;
+    assert!(html_page.contains("<h1>Issue #1: &lt;Hello&gt;</h1>"));
+    assert!(html_page.contains("<p>Newsletter body as <strong>Markdown</strong></p>"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn drafts_are_not_in_the_archive() -> Result<()> {
+    let app = spawn_app().await?;
+    app.login().await?;
+    app.create_draft_issue().await?;
+    let slug = issue_slug(&app).await?;
+
+    let html_page = app.get_archive_html().await?;
+    assert!(!html_page.contains("Issue #1"));
+
+    let response = app.get_archived_issue(&slug).await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+#[tokio::test]
+async fn unknown_issues_are_not_found() -> Result<()> {
+    let app = spawn_app().await?;
+
+    let response = app.get_archived_issue("no-such-issue").await?;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+#[tokio::test]
+async fn the_feed_contains_published_issues() -> Result<()> {
+    let app = spawn_app().await?;
+    publish_issue(&app).await?;
+    let slug = issue_slug(&app).await?;
+
+    let response = app.get_feed().await?;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["Content-Type"], "application/atom+xml");
+    let feed = response.text().await?;
+    assert!(feed.contains("<title>Issue #1: &lt;Hello&gt;</title>"));
+    assert!(feed.contains(&format!("/archive/{slug}\"/>")));
+    assert!(
+        feed.contains(
+            "&lt;p&gt;Newsletter body as &lt;strong&gt;Markdown&lt;/strong&gt;&lt;/p&gt;"
+        )
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn issues_sent_as_raw_html_are_archived_as_plain_text() -> Result<()> {
+    let app = spawn_app().await?;
+    let response = app
+        .post_newsletters(&serde_json::json!({
+            "title": "Issue #1",
+            "content": {
+                "text": "Newsletter body as <plain> text\n\nSecond paragraph",
+                "html": "<p>Newsletter body as HTML</p><script>alert(1)</script>",
+            }
+        }))
+        .await?;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    let slug = issue_slug(&app).await?;
+
+    let html_page = app.get_archive_html().await?;
+    assert!(html_page.contains("Issue #1"));
+    let html_page = app.get_archived_issue(&slug).await?.text().await?;
+    assert!(!html_page.contains("<script>"));
+    assert!(!html_page.contains("Newsletter body as HTML"));
+    assert!(html_page.contains("<p>Newsletter body as &lt;plain&gt; text</p>"));
+    assert!(html_page.contains("<p>Second paragraph</p>"));
+    let feed = app.get_feed().await?.text().await?;
+    assert!(feed.contains("Issue #1"));
+    assert!(!feed.contains("script"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn issues_still_being_delivered_are_not_archived() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    publish_issue(&app).await?;
+    let slug = issue_slug(&app).await?;
+
+    let html_page = app.get_archive_html().await?;
+    assert!(!html_page.contains("Issue #1"));
+    let response = app.get_archived_issue(&slug).await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let feed = app.get_feed().await?.text().await?;
+    assert!(!feed.contains("Issue #1"));
+
+    Mock::given(path("/email/batch"))
+        .respond_with(AcceptBatch)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await?;
+
+    let html_page = app.get_archive_html().await?;
+    assert!(html_page.contains("Issue #1"));
+    Ok(())
+}
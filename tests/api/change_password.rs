@@ -0,0 +1,124 @@
+use anyhow::Result;
+use uuid::Uuid;
+
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_change_password_form() -> Result<()> {
+    let app = spawn_app().await?;
+
+    let response = app.get_change_password().await?;
+
+    assert_is_redirect_to(&response, "/login");
+    Ok(())
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_change_your_password() -> Result<()> {
+    let app = spawn_app().await?;
+    let new_password = Uuid::new_v4().to_string();
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": Uuid::new_v4().to_string(),
+            "new_password": &new_password,
+            "new_password_check": &new_password,
+        }))
+        .await?;
+
+    assert_is_redirect_to(&response, "/login");
+    Ok(())
+}
+
+#[tokio::test]
+async fn new_password_fields_must_match() -> Result<()> {
+    let app = spawn_app().await?;
+    app.login().await?;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": &app.test_user.password,
+            "new_password": Uuid::new_v4().to_string(),
+            "new_password_check": Uuid::new_v4().to_string(),
+        }))
+        .await?;
+    assert_is_redirect_to(&response, "/admin/password");
+
+    let html_page = app.get_change_password_html().await?;
+    assert!(html_page.contains(
+        "<p><i>You entered two different new passwords - the field values must match.</i></p>"
+    ));
+    Ok(())
+}
+
+#[tokio::test]
+async fn new_password_must_have_a_valid_length() -> Result<()> {
+    let app = spawn_app().await?;
+    app.login().await?;
+
+    for new_password in ["too-short".to_owned(), "a".repeat(129)] {
+        let response = app
+            .post_change_password(&serde_json::json!({
+                "current_password": &app.test_user.password,
+                "new_password": &new_password,
+                "new_password_check": &new_password,
+            }))
+            .await?;
+        assert_is_redirect_to(&response, "/admin/password");
+
+        let html_page = app.get_change_password_html().await?;
+        assert!(html_page.contains(
+            "<p><i>The new password must be between 12 and 128 characters long.</i></p>"
+        ));
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn current_password_must_be_valid() -> Result<()> {
+    let app = spawn_app().await?;
+    let new_password = Uuid::new_v4().to_string();
+    app.login().await?;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": Uuid::new_v4().to_string(),
+            "new_password": &new_password,
+            "new_password_check": &new_password,
+        }))
+        .await?;
+    assert_is_redirect_to(&response, "/admin/password");
+
+    let html_page = app.get_change_password_html().await?;
+    assert!(html_page.contains("<p><i>The current password is incorrect.</i></p>"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn changing_password_works() -> Result<()> {
+    let app = spawn_app().await?;
+    let new_password = Uuid::new_v4().to_string();
+    app.login().await?;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": &app.test_user.password,
+            "new_password": &new_password,
+            "new_password_check": &new_password,
+        }))
+        .await?;
+    assert_is_redirect_to(&response, "/admin/password");
+
+    let html_page = app.get_change_password_html().await?;
+    assert!(html_page.contains("<p><i>Your password has been changed.</i></p>"));
+
+    app.post_logout().await?;
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &new_password,
+        }))
+        .await?;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    Ok(())
+}
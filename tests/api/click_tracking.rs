@@ -0,0 +1,227 @@
+use anyhow::Result;
+use bulletin::domain::TrackingToken;
+use reqwest::StatusCode;
+
+use crate::helpers::{TestApp, create_confirmed_subscriber, spawn_app};
+
+struct Clicks {
+    n_clicks: i32,
+    clicked: bool,
+}
+
+async fn clicks(app: &TestApp) -> Result<Clicks> {
+    Ok(sqlx::query_as!(
+        Clicks,
+        r#"SELECT n_clicks, first_clicked_at IS NOT NULL AS "clicked!" FROM deliveries"#
+    )
+    .fetch_one(&app.db_pool)
+    .await?)
+}
+
+#[tokio::test]
+async fn issues_do_not_track_clicks_by_default() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+
+    let issue = app.deliver_newsletter(&serde_json::json!({})).await?;
+
+    assert!(
+        issue
+            .html
+            .contains(r#"href="https://example.com/post?a=1&amp;b=2""#)
+    );
+    assert!(issue.text.contains("https://example.com/post?a=1&b=2"));
+    assert!(!issue.html.contains("/t/c/"));
+    assert!(!issue.text.contains("/t/c/"));
+    let links = sqlx::query!("SELECT url FROM issue_links")
+        .fetch_all(&app.db_pool)
+        .await?;
+    assert!(links.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn links_in_both_bodies_are_rewritten() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+
+    let issue = app
+        .deliver_newsletter(&serde_json::json!({ "track_clicks": true }))
+        .await?;
+
+    assert!(!issue.html.contains("https://example.com/post"));
+    assert!(!issue.text.contains("https://example.com/post"));
+    app.get_link(&issue.html, "/t/c/");
+    app.get_link(&issue.text, "/t/c/");
+    Ok(())
+}
+
+#[tokio::test]
+async fn following_a_tracked_link_records_the_click_and_redirects() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    let issue = app
+        .deliver_newsletter(&serde_json::json!({ "track_clicks": true }))
+        .await?;
+
+    let response = app
+        .api_client
+        .get(app.get_link(&issue.html, "/t/c/"))
+        .send()
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(
+        response.headers()["Location"],
+        "https://example.com/post?a=1&b=2"
+    );
+    let clicks = clicks(&app).await?;
+    assert!(clicks.clicked);
+    assert_eq!(clicks.n_clicks, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn both_bodies_link_to_the_same_tracked_url() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    let issue = app
+        .deliver_newsletter(&serde_json::json!({ "track_clicks": true }))
+        .await?;
+
+    for body in [&issue.html, &issue.text] {
+        let response = app
+            .api_client
+            .get(app.get_link(body, "/t/c/"))
+            .send()
+            .await?;
+        assert_eq!(response.status(), StatusCode::FOUND);
+    }
+
+    let link = sqlx::query!("SELECT n_clicks FROM issue_links")
+        .fetch_one(&app.db_pool)
+        .await?;
+    assert_eq!(link.n_clicks, 2);
+    assert_eq!(clicks(&app).await?.n_clicks, 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_forged_tracking_token_is_not_redirected() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    let issue = app
+        .deliver_newsletter(&serde_json::json!({ "track_clicks": true }))
+        .await?;
+    let mut tracked_link = app.get_link(&issue.html, "/t/c/");
+    let forged = tracked_link.path().replacen("/t/c/", "/t/c/x", 1);
+    tracked_link.set_path(&forged);
+
+    let response = app.api_client.get(tracked_link).send().await?;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(clicks(&app).await?.n_clicks, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn only_links_recorded_when_the_issue_was_published_are_followed() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    app.deliver_newsletter(&serde_json::json!({ "track_clicks": true }))
+        .await?;
+    let delivery = sqlx::query!(
+        r#"
+        SELECT newsletter_issue_id, subscriptions.id AS subscriber_id
+        FROM deliveries
+        JOIN subscriptions ON subscriptions.email = deliveries.subscriber_email
+        "#
+    )
+    .fetch_one(&app.db_pool)
+    .await?;
+    // Correctly signed, but for a link the issue never had.
+    let token = TrackingToken::for_link(
+        delivery.newsletter_issue_id,
+        delivery.subscriber_id,
+        uuid::Uuid::new_v4(),
+        &app.hmac_secret,
+    );
+
+    let response = app
+        .api_client
+        .get(format!("{}/t/c/{}", app.address, token.as_ref()))
+        .send()
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(clicks(&app).await?.n_clicks, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn the_deliveries_page_shows_clicks_per_link() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    let issue = app
+        .deliver_newsletter(&serde_json::json!({ "track_clicks": true }))
+        .await?;
+    app.api_client
+        .get(app.get_link(&issue.html, "/t/c/"))
+        .send()
+        .await?;
+    app.login().await?;
+
+    let row = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await?;
+    let html_page = app
+        .get_issue_deliveries(&format!("/admin/issues/{}", row.newsletter_issue_id))
+        .await?
+        .text()
+        .await?;
+
+    assert!(html_page.contains("<tr><th>Clicked</th><td>1</td></tr>"));
+    assert!(html_page.contains("<tr><th>Click rate</th><td>100.0%</td></tr>"));
+    assert!(html_page.contains("<tr><td>https://example.com/post?a=1&amp;b=2</td><td>1</td></tr>"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn the_deliveries_page_says_when_clicks_are_not_tracked() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    app.deliver_newsletter(&serde_json::json!({})).await?;
+    app.login().await?;
+
+    let row = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await?;
+    let html_page = app
+        .get_issue_deliveries(&format!("/admin/issues/{}", row.newsletter_issue_id))
+        .await?
+        .text()
+        .await?;
+
+    assert!(html_page.contains("<p>Clicks are not tracked for this issue.</p>"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn drafts_can_opt_in_to_click_tracking() -> Result<()> {
+    let app = spawn_app().await?;
+    app.login().await?;
+
+    let response = app
+        .post_admin_issue(&serde_json::json!({
+            "title": "Issue #1",
+            "markdown": "Some *news*.",
+            "track_clicks": true,
+        }))
+        .await?;
+    let issue_path = response.headers()["Location"].to_str()?.to_owned();
+
+    let html_page = app.get_admin_issue(&issue_path).await?.text().await?;
+    assert!(html_page.contains(r#"name="track_clicks" value="true" checked"#));
+    assert!(!html_page.contains(r#"name="track_opens" value="true" checked"#));
+    Ok(())
+}
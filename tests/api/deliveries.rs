@@ -0,0 +1,213 @@
+use anyhow::Result;
+use reqwest::StatusCode;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{
+    AcceptBatch, TestApp, assert_is_redirect_to, create_confirmed_subscriber, spawn_app,
+};
+
+struct Delivery {
+    status: String,
+    n_attempts: i16,
+    provider_message_id: Option<String>,
+    last_error: Option<String>,
+}
+
+async fn publish_newsletter(app: &TestApp) -> Result<()> {
+    let response = app
+        .post_newsletters(&serde_json::json!({
+            "title": "Newsletter title",
+            "content": {
+                "text": "Newsletter body as plain text",
+                "html": "<p>Newsletter body as HTML</p>",
+            }
+        }))
+        .await?;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    Ok(())
+}
+
+/// The only delivery in the database.
+async fn delivery(app: &TestApp) -> Result<Delivery> {
+    Ok(sqlx::query_as!(
+        Delivery,
+        r#"
+        SELECT status::TEXT AS "status!", n_attempts, provider_message_id, last_error
+        FROM deliveries
+        "#,
+    )
+    .fetch_one(&app.db_pool)
+    .await?)
+}
+
+/// The admin path of the only issue in the database.
+async fn issue_path(app: &TestApp) -> Result<String> {
+    let row = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await?;
+    Ok(format!("/admin/issues/{}", row.newsletter_issue_id))
+}
+
+#[tokio::test]
+async fn published_issues_have_a_pending_delivery_per_confirmed_subscriber() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+
+    publish_newsletter(&app).await?;
+
+    let delivery = delivery(&app).await?;
+    assert_eq!(delivery.status, "pending");
+    assert_eq!(delivery.n_attempts, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn successful_deliveries_record_the_provider_message_id() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(serde_json::json!([{
+                "ErrorCode": 0,
+                "Message": "OK",
+                "MessageID": "b7bc2f4a-e38e-4336-af7d-e6c392c2f817",
+            }])),
+        )
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter(&app).await?;
+    app.dispatch_all_pending_emails().await?;
+
+    let delivery = delivery(&app).await?;
+    assert_eq!(delivery.status, "sent");
+    assert_eq!(delivery.n_attempts, 1);
+    assert_eq!(
+        delivery.provider_message_id.as_deref(),
+        Some("b7bc2f4a-e38e-4336-af7d-e6c392c2f817")
+    );
+    assert_eq!(delivery.last_error, None);
+    Ok(())
+}
+
+#[tokio::test]
+async fn deliveries_stay_pending_after_a_transient_failure() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter(&app).await?;
+    app.dispatch_all_pending_emails().await?;
+
+    let delivery = delivery(&app).await?;
+    assert_eq!(delivery.status, "pending");
+    assert_eq!(delivery.n_attempts, 1);
+    assert!(delivery.last_error.unwrap().contains("500"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn deliveries_fail_after_a_permanent_failure() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(422))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter(&app).await?;
+    app.dispatch_all_pending_emails().await?;
+
+    let delivery = delivery(&app).await?;
+    assert_eq!(delivery.status, "failed");
+    assert_eq!(delivery.n_attempts, 1);
+    assert!(delivery.last_error.unwrap().contains("422"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn deliveries_to_subscribers_who_left_are_skipped() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+
+    Mock::given(path("/email/batch"))
+        .respond_with(AcceptBatch)
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter(&app).await?;
+    sqlx::query!("UPDATE subscriptions SET status = 'unsubscribed'")
+        .execute(&app.db_pool)
+        .await?;
+    app.dispatch_all_pending_emails().await?;
+
+    let delivery = delivery(&app).await?;
+    assert_eq!(delivery.status, "skipped");
+    assert_eq!(delivery.n_attempts, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_deliveries() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    publish_newsletter(&app).await?;
+
+    let response = app.get_issue_deliveries(&issue_path(&app).await?).await?;
+
+    assert_is_redirect_to(&response, "/login");
+    Ok(())
+}
+
+#[tokio::test]
+async fn the_deliveries_page_summarises_deliveries() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(422))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter(&app).await?;
+    app.dispatch_all_pending_emails().await?;
+    app.login().await?;
+
+    let response = app.get_issue_deliveries(&issue_path(&app).await?).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let html_page = response.text().await?;
+    assert!(html_page.contains("<tr><th>Pending</th><td>0</td></tr>"));
+    assert!(html_page.contains("<tr><th>Sent</th><td>0</td></tr>"));
+    assert!(html_page.contains("<tr><th>Failed</th><td>1</td></tr>"));
+    assert!(html_page.contains("ursula_le_guin@gmail.com"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn deliveries_of_a_missing_issue_are_not_found() -> Result<()> {
+    let app = spawn_app().await?;
+    app.login().await?;
+
+    let response = app
+        .get_issue_deliveries(&format!("/admin/issues/{}", uuid::Uuid::new_v4()))
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    Ok(())
+}
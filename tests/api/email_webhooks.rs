@@ -0,0 +1,279 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+use bulletin::domain::SubscriptionStatus;
+use chrono::{DateTime, TimeDelta, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::StatusCode;
+use secrecy::ExposeSecret;
+use sha2::Sha256;
+use uuid::Uuid;
+use wiremock::matchers::any;
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{TestApp, create_confirmed_subscriber, spawn_app};
+
+const SUBSCRIBER_EMAIL: &str = "ursula_le_guin@gmail.com";
+
+async fn subscriber_status(app: &TestApp) -> Result<SubscriptionStatus> {
+    let saved = sqlx::query!(r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await?;
+    Ok(saved.status)
+}
+
+/// Wraps `event_data` the way Mailgun does, signed with `signing_key` just now.
+fn mailgun_webhook(signing_key: &str, event_data: &serde_json::Value) -> serde_json::Value {
+    signed_mailgun_webhook(signing_key, Utc::now(), event_data)
+}
+
+fn signed_mailgun_webhook(
+    signing_key: &str,
+    signed_at: DateTime<Utc>,
+    event_data: &serde_json::Value,
+) -> serde_json::Value {
+    let timestamp = signed_at.timestamp().to_string();
+    let token = Uuid::new_v4().simple().to_string();
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(signing_key.as_bytes()).expect("HMAC accepts any key");
+    mac.update(timestamp.as_bytes());
+    mac.update(token.as_bytes());
+    let signature = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        });
+
+    serde_json::json!({
+        "signature": {
+            "timestamp": timestamp,
+            "token": token,
+            "signature": signature,
+        },
+        "event-data": event_data,
+    })
+}
+
+#[tokio::test]
+async fn a_postmark_hard_bounce_bounces_the_subscriber() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+
+    let response = app
+        .post_postmark_webhook(&serde_json::json!({
+            "RecordType": "Bounce",
+            "Type": "HardBounce",
+            "TypeCode": 1,
+            "Email": SUBSCRIBER_EMAIL,
+            "Inactive": true,
+        }))
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(subscriber_status(&app).await?, SubscriptionStatus::Bounced);
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_postmark_soft_bounce_leaves_the_subscriber_confirmed() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+
+    let response = app
+        .post_postmark_webhook(&serde_json::json!({
+            "RecordType": "Bounce",
+            "Type": "SoftBounce",
+            "TypeCode": 4096,
+            "Email": SUBSCRIBER_EMAIL,
+            "Inactive": false,
+        }))
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        subscriber_status(&app).await?,
+        SubscriptionStatus::Confirmed
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_postmark_spam_complaint_marks_the_subscriber_as_complained() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+
+    let response = app
+        .post_postmark_webhook(&serde_json::json!({
+            "RecordType": "SpamComplaint",
+            "Email": SUBSCRIBER_EMAIL,
+        }))
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        subscriber_status(&app).await?,
+        SubscriptionStatus::Complained
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn postmark_webhooks_without_valid_credentials_are_rejected() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    let body = serde_json::json!({
+        "RecordType": "SpamComplaint",
+        "Email": SUBSCRIBER_EMAIL,
+    });
+
+    let response = app.post_email_webhook("postmark", &body).await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .api_client
+        .post(format!("{}/webhooks/email/postmark", &app.address))
+        .basic_auth(&app.webhooks.postmark_username, Some("wrong-password"))
+        .json(&body)
+        .send()
+        .await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    assert_eq!(
+        subscriber_status(&app).await?,
+        SubscriptionStatus::Confirmed
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_mailgun_permanent_failure_bounces_the_subscriber() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+
+    let body = mailgun_webhook(
+        app.webhooks.mailgun_signing_key.expose_secret(),
+        &serde_json::json!({
+            "event": "failed",
+            "severity": "permanent",
+            "recipient": SUBSCRIBER_EMAIL,
+        }),
+    );
+    let response = app.post_email_webhook("mailgun", &body).await?;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(subscriber_status(&app).await?, SubscriptionStatus::Bounced);
+    Ok(())
+}
+
+#[tokio::test]
+async fn mailgun_webhooks_with_an_invalid_signature_are_rejected() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+
+    let body = mailgun_webhook(
+        "another-signing-key",
+        &serde_json::json!({
+            "event": "complained",
+            "recipient": SUBSCRIBER_EMAIL,
+        }),
+    );
+    let response = app.post_email_webhook("mailgun", &body).await?;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        subscriber_status(&app).await?,
+        SubscriptionStatus::Confirmed
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn replayed_mailgun_webhooks_are_rejected() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+
+    let body = mailgun_webhook(
+        app.webhooks.mailgun_signing_key.expose_secret(),
+        &serde_json::json!({
+            "event": "delivered",
+            "recipient": SUBSCRIBER_EMAIL,
+        }),
+    );
+    let response = app.post_email_webhook("mailgun", &body).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.post_email_webhook("mailgun", &body).await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    Ok(())
+}
+
+#[tokio::test]
+async fn stale_mailgun_webhooks_are_rejected() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+
+    let body = signed_mailgun_webhook(
+        app.webhooks.mailgun_signing_key.expose_secret(),
+        Utc::now() - TimeDelta::minutes(10),
+        &serde_json::json!({
+            "event": "complained",
+            "recipient": SUBSCRIBER_EMAIL,
+        }),
+    );
+    let response = app.post_email_webhook("mailgun", &body).await?;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        subscriber_status(&app).await?,
+        SubscriptionStatus::Confirmed
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn webhooks_from_unknown_providers_are_not_found() -> Result<()> {
+    let app = spawn_app().await?;
+
+    let response = app
+        .post_email_webhook("carrier-pigeon", &serde_json::json!({}))
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+#[tokio::test]
+async fn bounced_subscribers_do_not_receive_newsletters() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    app.post_postmark_webhook(&serde_json::json!({
+        "RecordType": "Bounce",
+        "Email": SUBSCRIBER_EMAIL,
+        "Inactive": true,
+    }))
+    .await?
+    .error_for_status()?;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_newsletters(&serde_json::json!({
+            "title": "Newsletter title",
+            "content": {
+                "text": "Newsletter body as plain text",
+                "html": "<p>Newsletter body as HTML</p>",
+            }
+        }))
+        .await?;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    app.dispatch_all_pending_emails().await?;
+
+    Ok(())
+}
@@ -1,14 +1,24 @@
-use std::sync::LazyLock;
+use std::sync::{Arc, LazyLock, Mutex};
 
 use anyhow::Result;
-use bulletin::Application;
-use bulletin::configuration::{self, DatabaseSettings};
+use bulletin::authentication::compute_password_hash;
+use bulletin::clock::Clock;
+use bulletin::configuration::{self, DatabaseSettings, WebhookSettings};
+use bulletin::email_client::{RetryPolicy, SuppressingSender};
+use bulletin::email_outbox_worker::try_dispatch_email;
+use bulletin::email_templates::EmailTemplates;
+use bulletin::issue_delivery_worker::{ExecutionOutcome, try_execute_task};
+use bulletin::issue_scheduler::try_promote_due_issue;
 use bulletin::startup::get_connection_pool;
 use bulletin::telemetry::{Formatter, get_subscriber, init_subscriber};
+use bulletin::{Application, EmailClient};
+use chrono::{DateTime, TimeDelta, Utc};
 use reqwest::header::CONTENT_TYPE;
+use secrecy::{ExposeSecret, SecretString};
 use sqlx::{Connection, Executor, PgConnection, PgPool};
 use uuid::Uuid;
-use wiremock::MockServer;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
 
 static TRACING: LazyLock<()> = LazyLock::new(|| {
     let default_log_level = "info".to_owned();
@@ -32,55 +42,600 @@ static TRACING: LazyLock<()> = LazyLock::new(|| {
     }
 });
 
+/// A clock that only moves when told to.
+pub struct FakeClock(Mutex<DateTime<Utc>>);
+
+impl FakeClock {
+    pub fn starting_now() -> Self {
+        Self(Mutex::new(Utc::now()))
+    }
+
+    pub fn advance(&self, by: TimeDelta) {
+        *self.0.lock().unwrap() += by;
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.0.lock().unwrap()
+    }
+}
+
 pub struct ConfirmationLinks {
     pub html: reqwest::Url,
     pub plain_text: reqwest::Url,
 }
 
+/// What the last confirmed subscriber was sent when an issue was delivered.
+pub struct DeliveredIssue {
+    pub html: String,
+    pub text: String,
+    pub unsubscribe_link: reqwest::Url,
+}
+
+pub struct TestUser {
+    pub user_id: Uuid,
+    pub username: String,
+    pub password: String,
+}
+
+impl TestUser {
+    pub fn generate() -> Self {
+        Self {
+            user_id: Uuid::new_v4(),
+            username: Uuid::new_v4().to_string(),
+            password: Uuid::new_v4().to_string(),
+        }
+    }
+
+    async fn store(&self, pool: &PgPool) -> Result<()> {
+        let password_hash = compute_password_hash(&SecretString::from(self.password.as_str()))?;
+        sqlx::query!(
+            "INSERT INTO users (user_id, username, password_hash) VALUES ($1, $2, $3)",
+            self.user_id,
+            self.username,
+            password_hash.expose_secret(),
+        )
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+}
+
 pub struct TestApp {
     pub address: String,
     pub db_pool: PgPool,
     pub email_server: MockServer,
     pub port: u16,
+    pub email_client: EmailClient,
+    pub templates: EmailTemplates,
+    pub base_url: String,
+    pub hmac_secret: SecretString,
+    pub retry_policy: RetryPolicy,
+    pub webhooks: WebhookSettings,
+    pub test_user: TestUser,
+    pub api_client: reqwest::Client,
 }
 
 impl TestApp {
+    /// Also runs the outbox dispatcher, so the confirmation email (if any) has
+    /// reached the email server by the time this returns.
     pub async fn post_subscriptions(&self, body: &str) -> Result<reqwest::Response> {
-        Ok(reqwest::Client::new()
+        let response = self.subscriptions_request(body).send().await?;
+        self.dispatch_outbox().await?;
+        Ok(response)
+    }
+
+    pub async fn post_subscriptions_with_key(
+        &self,
+        body: &str,
+        idempotency_key: &str,
+    ) -> Result<reqwest::Response> {
+        let response = self
+            .subscriptions_request(body)
+            .header("Idempotency-Key", idempotency_key)
+            .send()
+            .await?;
+        self.dispatch_outbox().await?;
+        Ok(response)
+    }
+
+    pub fn subscriptions_request(&self, body: &str) -> reqwest::RequestBuilder {
+        reqwest::Client::new()
             .post(format!("{}/subscriptions", &self.address))
             .header(
                 CONTENT_TYPE,
                 mime::APPLICATION_WWW_FORM_URLENCODED.to_string(),
             )
             .body(body.to_owned())
+    }
+
+    pub async fn dispatch_all_pending_emails(&self) -> Result<()> {
+        loop {
+            let outcome = try_execute_task(
+                &self.db_pool,
+                &self.email_client,
+                &self.templates,
+                &self.base_url,
+                &self.hmac_secret,
+                &self.retry_policy,
+            )
+            .await?;
+            if matches!(outcome, ExecutionOutcome::EmptyQueue) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn dispatch_outbox(&self) -> Result<()> {
+        loop {
+            let outcome =
+                try_dispatch_email(&self.db_pool, &self.email_client, &self.retry_policy).await?;
+            if matches!(outcome, ExecutionOutcome::EmptyQueue) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn post_newsletters(&self, body: &serde_json::Value) -> Result<reqwest::Response> {
+        Ok(self.newsletters_request(body).send().await?)
+    }
+
+    pub async fn post_newsletters_with_key(
+        &self,
+        body: &serde_json::Value,
+        idempotency_key: &str,
+    ) -> Result<reqwest::Response> {
+        Ok(self
+            .newsletters_request(body)
+            .header("Idempotency-Key", idempotency_key)
+            .send()
+            .await?)
+    }
+
+    fn newsletters_request(&self, body: &serde_json::Value) -> reqwest::RequestBuilder {
+        reqwest::Client::new()
+            .post(format!("{}/newsletters", &self.address))
+            .basic_auth(&self.test_user.username, Some(&self.test_user.password))
+            .json(body)
+    }
+
+    pub fn get_unsubscribe_link(&self, email_request: &wiremock::Request) -> Result<reqwest::Url> {
+        let body: serde_json::Value = serde_json::from_slice(&email_request.body)?;
+        let header = body[0]["Headers"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|h| h["Name"] == "List-Unsubscribe")
+            .unwrap();
+        let raw_link = header["Value"]
+            .as_str()
+            .unwrap()
+            .trim_start_matches('<')
+            .trim_end_matches('>');
+        let mut unsubscribe_link = reqwest::Url::parse(raw_link)?;
+        assert_eq!(unsubscribe_link.host_str().unwrap(), "127.0.0.1");
+        unsubscribe_link.set_port(Some(self.port)).unwrap();
+        Ok(unsubscribe_link)
+    }
+
+    /// Submits the confirmation page behind an unsubscribe link.
+    pub async fn post_unsubscribe(
+        &self,
+        unsubscribe_link: impl reqwest::IntoUrl,
+    ) -> Result<reqwest::Response> {
+        Ok(self
+            .api_client
+            .post(unsubscribe_link)
+            .form(&[] as &[(&str, &str)])
+            .send()
+            .await?)
+    }
+
+    pub async fn post_email_webhook(
+        &self,
+        provider: &str,
+        body: &serde_json::Value,
+    ) -> Result<reqwest::Response> {
+        Ok(self
+            .api_client
+            .post(format!("{}/webhooks/email/{provider}", &self.address))
+            .json(body)
+            .send()
+            .await?)
+    }
+
+    /// Sends the credentials Postmark is configured with.
+    pub async fn post_postmark_webhook(
+        &self,
+        body: &serde_json::Value,
+    ) -> Result<reqwest::Response> {
+        Ok(self
+            .api_client
+            .post(format!("{}/webhooks/email/postmark", &self.address))
+            .basic_auth(
+                &self.webhooks.postmark_username,
+                Some(self.webhooks.postmark_password.expose_secret()),
+            )
+            .json(body)
+            .send()
+            .await?)
+    }
+
+    pub async fn post_login<Body>(&self, body: &Body) -> Result<reqwest::Response>
+    where
+        Body: serde::Serialize + Sync + ?Sized,
+    {
+        Ok(self
+            .api_client
+            .post(format!("{}/login", &self.address))
+            .form(body)
+            .send()
+            .await?)
+    }
+
+    pub async fn get_login_html(&self) -> Result<String> {
+        Ok(self
+            .api_client
+            .get(format!("{}/login", &self.address))
+            .send()
+            .await?
+            .text()
+            .await?)
+    }
+
+    pub async fn get_admin_dashboard(&self) -> Result<reqwest::Response> {
+        Ok(self
+            .api_client
+            .get(format!("{}/admin/dashboard", &self.address))
+            .send()
+            .await?)
+    }
+
+    pub async fn get_admin_dashboard_html(&self) -> Result<String> {
+        Ok(self.get_admin_dashboard().await?.text().await?)
+    }
+
+    pub async fn post_logout(&self) -> Result<reqwest::Response> {
+        Ok(self
+            .api_client
+            .post(format!("{}/admin/logout", &self.address))
+            .send()
+            .await?)
+    }
+
+    pub async fn get_change_password(&self) -> Result<reqwest::Response> {
+        Ok(self
+            .api_client
+            .get(format!("{}/admin/password", &self.address))
+            .send()
+            .await?)
+    }
+
+    pub async fn get_change_password_html(&self) -> Result<String> {
+        Ok(self.get_change_password().await?.text().await?)
+    }
+
+    pub async fn post_change_password<Body>(&self, body: &Body) -> Result<reqwest::Response>
+    where
+        Body: serde::Serialize + Sync + ?Sized,
+    {
+        Ok(self
+            .api_client
+            .post(format!("{}/admin/password", &self.address))
+            .form(body)
+            .send()
+            .await?)
+    }
+
+    pub async fn get_archive_html(&self) -> Result<String> {
+        Ok(reqwest::get(format!("{}/archive", &self.address))
+            .await?
+            .text()
+            .await?)
+    }
+
+    pub async fn get_archived_issue(&self, slug: &str) -> Result<reqwest::Response> {
+        Ok(reqwest::get(format!("{}/archive/{slug}", &self.address)).await?)
+    }
+
+    pub async fn get_feed(&self) -> Result<reqwest::Response> {
+        Ok(reqwest::get(format!("{}/feed.xml", &self.address)).await?)
+    }
+
+    pub async fn get_admin_issues_html(&self) -> Result<String> {
+        Ok(self
+            .api_client
+            .get(format!("{}/admin/issues", &self.address))
+            .send()
+            .await?
+            .text()
+            .await?)
+    }
+
+    pub async fn post_admin_issue<Body>(&self, body: &Body) -> Result<reqwest::Response>
+    where
+        Body: serde::Serialize + Sync + ?Sized,
+    {
+        Ok(self
+            .api_client
+            .post(format!("{}/admin/issues", &self.address))
+            .form(body)
+            .send()
+            .await?)
+    }
+
+    pub async fn get_admin_issue(&self, issue_path: &str) -> Result<reqwest::Response> {
+        Ok(self
+            .api_client
+            .get(format!("{}{issue_path}", &self.address))
+            .send()
+            .await?)
+    }
+
+    pub async fn post_admin_issue_update<Body>(
+        &self,
+        issue_path: &str,
+        body: &Body,
+    ) -> Result<reqwest::Response>
+    where
+        Body: serde::Serialize + Sync + ?Sized,
+    {
+        Ok(self
+            .api_client
+            .post(format!("{}{issue_path}", &self.address))
+            .form(body)
+            .send()
+            .await?)
+    }
+
+    pub async fn get_admin_issue_preview_html(&self, issue_path: &str) -> Result<String> {
+        Ok(self
+            .api_client
+            .get(format!("{}{issue_path}/preview", &self.address))
+            .send()
+            .await?
+            .text()
+            .await?)
+    }
+
+    pub async fn get_issue_deliveries(&self, issue_path: &str) -> Result<reqwest::Response> {
+        Ok(self
+            .api_client
+            .get(format!("{}{issue_path}/deliveries", &self.address))
+            .send()
+            .await?)
+    }
+
+    pub async fn get_suppressions(&self) -> Result<reqwest::Response> {
+        Ok(self
+            .api_client
+            .get(format!("{}/admin/suppressions", &self.address))
+            .send()
+            .await?)
+    }
+
+    pub async fn get_suppressions_html(&self) -> Result<String> {
+        Ok(self.get_suppressions().await?.text().await?)
+    }
+
+    pub async fn post_suppression(&self, address: &str) -> Result<reqwest::Response> {
+        Ok(self
+            .api_client
+            .post(format!("{}/admin/suppressions", &self.address))
+            .form(&[("address", address)])
+            .send()
+            .await?)
+    }
+
+    pub async fn post_lift_suppression(&self, address: &str) -> Result<reqwest::Response> {
+        Ok(self
+            .api_client
+            .post(format!("{}/admin/suppressions/lift", &self.address))
+            .form(&[("address", address)])
+            .send()
+            .await?)
+    }
+
+    pub async fn post_publish_issue(&self, issue_path: &str) -> Result<reqwest::Response> {
+        Ok(self
+            .api_client
+            .post(format!("{}{issue_path}/publish", &self.address))
+            .send()
+            .await?)
+    }
+
+    pub async fn post_schedule_issue<Body>(
+        &self,
+        issue_path: &str,
+        body: &Body,
+    ) -> Result<reqwest::Response>
+    where
+        Body: serde::Serialize + Sync + ?Sized,
+    {
+        Ok(self
+            .api_client
+            .post(format!("{}{issue_path}/schedule", &self.address))
+            .form(body)
+            .send()
+            .await?)
+    }
+
+    pub async fn post_cancel_issue(&self, issue_path: &str) -> Result<reqwest::Response> {
+        Ok(self
+            .api_client
+            .post(format!("{}{issue_path}/cancel", &self.address))
             .send()
             .await?)
     }
 
+    /// Creates a draft and returns the path of its edit page.
+    pub async fn create_draft_issue(&self) -> Result<String> {
+        let response = self
+            .post_admin_issue(&serde_json::json!({
+                "title": "Issue #1",
+                "markdown": "# Hello\n\nSome *news*.",
+            }))
+            .await?;
+        assert_eq!(response.status().as_u16(), 303);
+        let location = response.headers()["Location"].to_str()?.to_owned();
+        assert!(location.starts_with("/admin/issues/"));
+        Ok(location)
+    }
+
+    /// The status of the only issue in the database.
+    pub async fn issue_status(&self) -> Result<String> {
+        let row = sqlx::query!(r#"SELECT status::TEXT AS "status!" FROM newsletter_issues"#)
+            .fetch_one(&self.db_pool)
+            .await?;
+        Ok(row.status)
+    }
+
+    pub async fn run_scheduler(&self, clock: &FakeClock) -> Result<()> {
+        loop {
+            let outcome = try_promote_due_issue(&self.db_pool, clock).await?;
+            if matches!(outcome, ExecutionOutcome::EmptyQueue) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn login(&self) -> Result<()> {
+        self.post_login(&serde_json::json!({
+            "username": &self.test_user.username,
+            "password": &self.test_user.password,
+        }))
+        .await?;
+        Ok(())
+    }
+
     pub fn get_confirmation_links(
         &self,
         email_request: &wiremock::Request,
     ) -> Result<ConfirmationLinks> {
         let body: serde_json::Value = serde_json::from_slice(&email_request.body)?;
 
-        let get_link = |s: &str| {
-            let links: Vec<_> = linkify::LinkFinder::new()
-                .links(s)
-                .filter(|l| *l.kind() == linkify::LinkKind::Url)
-                .collect();
-            assert_eq!(links.len(), 1);
-            let raw_link = links[0].as_str().to_owned();
-            let mut confirmation_link = reqwest::Url::parse(&raw_link).unwrap();
-            assert_eq!(confirmation_link.host_str().unwrap(), "127.0.0.1");
-            confirmation_link.set_port(Some(self.port)).unwrap();
-            confirmation_link
-        };
-
+        let get_link = |s: &str| self.get_link(s, "/subscriptions/confirm");
         let html = get_link(body["HtmlBody"].as_str().unwrap());
         let plain_text = get_link(body["TextBody"].as_str().unwrap());
 
         Ok(ConfirmationLinks { html, plain_text })
     }
+
+    /// The only link in `body` containing `pattern`, pointed at the test server.
+    pub fn get_link(&self, body: &str, pattern: &str) -> reqwest::Url {
+        let links: Vec<_> = linkify::LinkFinder::new()
+            .links(body)
+            .filter(|l| *l.kind() == linkify::LinkKind::Url && l.as_str().contains(pattern))
+            .collect();
+        assert_eq!(links.len(), 1);
+        let mut link = reqwest::Url::parse(links[0].as_str()).unwrap();
+        assert_eq!(link.host_str().unwrap(), "127.0.0.1");
+        link.set_port(Some(self.port)).unwrap();
+        link
+    }
+
+    /// Publishes an issue linking to `https://example.com/post?a=1&b=2`, with
+    /// `options` such as `track_opens` added to the request, and returns what
+    /// the confirmed subscriber was sent.
+    pub async fn deliver_newsletter(&self, options: &serde_json::Value) -> Result<DeliveredIssue> {
+        let _mock_guard = Mock::given(path("/email/batch"))
+            .and(method("POST"))
+            .respond_with(AcceptBatch)
+            .named("deliver newsletter")
+            .expect(1)
+            .mount_as_scoped(&self.email_server)
+            .await;
+        let mut newsletter_request_body = serde_json::json!({
+            "title": "Newsletter title",
+            "content": {
+                "text": "Read the post at https://example.com/post?a=1&b=2.",
+                "html": r#"<p>Read <a href="https://example.com/post?a=1&amp;b=2">the post</a>.</p>"#,
+            },
+        });
+        if let (Some(body), Some(options)) =
+            (newsletter_request_body.as_object_mut(), options.as_object())
+        {
+            body.extend(options.clone());
+        }
+        self.post_newsletters(&newsletter_request_body)
+            .await?
+            .error_for_status()?;
+        self.dispatch_all_pending_emails().await?;
+
+        let email_request = self
+            .email_server
+            .received_requests()
+            .await
+            .unwrap()
+            .pop()
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&email_request.body)?;
+        Ok(DeliveredIssue {
+            html: body[0]["HtmlBody"].as_str().unwrap().to_owned(),
+            text: body[0]["TextBody"].as_str().unwrap().to_owned(),
+            unsubscribe_link: self.get_unsubscribe_link(&email_request)?,
+        })
+    }
+}
+
+/// Answers a Postmark `/email/batch` request as if every email in it was
+/// accepted.
+pub struct AcceptBatch;
+
+impl wiremock::Respond for AcceptBatch {
+    fn respond(&self, request: &wiremock::Request) -> ResponseTemplate {
+        let emails: Vec<serde_json::Value> = request.body_json().unwrap();
+        let results: Vec<_> = emails
+            .iter()
+            .map(|_| {
+                serde_json::json!({
+                    "ErrorCode": 0,
+                    "Message": "OK",
+                    "MessageID": Uuid::new_v4(),
+                })
+            })
+            .collect();
+        ResponseTemplate::new(200).set_body_json(results)
+    }
+}
+
+pub async fn create_unconfirmed_subscriber(app: &TestApp) -> Result<ConfirmationLinks> {
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .named("create unconfirmed subscriber")
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.post_subscriptions(body).await?.error_for_status()?;
+
+    let email_request = &app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    app.get_confirmation_links(email_request)
+}
+
+pub async fn create_confirmed_subscriber(app: &TestApp) -> Result<()> {
+    let confirmation_link = create_unconfirmed_subscriber(app).await?;
+    reqwest::get(confirmation_link.html)
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+pub fn assert_is_redirect_to(response: &reqwest::Response, location: &str) {
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(response.headers()["Location"], location);
 }
 
 pub async fn spawn_app() -> Result<TestApp> {
@@ -98,7 +653,17 @@ pub async fn spawn_app() -> Result<TestApp> {
     configure_database(&configuration.database).await?;
     let db_pool = get_connection_pool(&configuration.database);
 
-    let application = Application::build(configuration)?;
+    let email_client: EmailClient = Arc::new(SuppressingSender::new(
+        configuration.email_client.clone().client(),
+        db_pool.clone(),
+    ));
+    let templates = EmailTemplates::load(&configuration.application.templates_directory)?;
+    let base_url = configuration.application.base_url.clone();
+    let hmac_secret = configuration.application.hmac_secret.clone();
+    let retry_policy = configuration.email_client.retry_policy();
+    let webhooks = configuration.webhooks.clone();
+
+    let application = Application::build(configuration).await?;
     let port = application.port();
     let router = application.router();
 
@@ -108,11 +673,28 @@ pub async fn spawn_app() -> Result<TestApp> {
 
     let server = axum_server::from_tcp(listener).serve(router.into_make_service());
     tokio::spawn(server);
+
+    let test_user = TestUser::generate();
+    test_user.store(&db_pool).await?;
+
+    let api_client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .cookie_store(true)
+        .build()?;
+
     Ok(TestApp {
         address,
         db_pool,
         email_server,
         port,
+        email_client,
+        templates,
+        base_url,
+        hmac_secret,
+        retry_policy,
+        webhooks,
+        test_user,
+        api_client,
     })
 }
 
@@ -1,5 +1,19 @@
 #![allow(clippy::unwrap_used)]
+mod admin_dashboard;
+mod admin_issues;
+mod archive;
+mod change_password;
+mod click_tracking;
+mod deliveries;
+mod email_webhooks;
 mod health;
 mod helpers;
+mod login;
+mod newsletters;
+mod open_tracking;
+mod scheduled_issues;
+mod subscription_cleanup;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_unsubscribe;
+mod suppressions;
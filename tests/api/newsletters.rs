@@ -0,0 +1,409 @@
+use anyhow::Result;
+use bulletin::configuration;
+use reqwest::StatusCode;
+use secrecy::ExposeSecret;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{
+    AcceptBatch, create_confirmed_subscriber, create_unconfirmed_subscriber, spawn_app,
+};
+
+fn newsletter_request_body() -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>",
+        }
+    })
+}
+
+#[tokio::test]
+async fn newsletters_are_not_delivered_to_unconfirmed_subscribers() -> Result<()> {
+    let app = spawn_app().await?;
+    create_unconfirmed_subscriber(&app).await?;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_newsletters(&newsletter_request_body()).await?;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    app.dispatch_all_pending_emails().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn newsletters_are_delivered_to_confirmed_subscribers() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(AcceptBatch)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_newsletters(&newsletter_request_body()).await?;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    app.dispatch_all_pending_emails().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn newsletters_are_sent_to_all_confirmed_subscribers_in_one_batch() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+        VALUES ($1, 'octavia_butler@gmail.com', 'octavia butler', NOW(), 'confirmed')
+        "#,
+        uuid::Uuid::new_v4(),
+    )
+    .execute(&app.db_pool)
+    .await?;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(AcceptBatch)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_newsletters(&newsletter_request_body()).await?;
+    app.dispatch_all_pending_emails().await?;
+
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_requests.last().unwrap().body)?;
+    let mut recipients: Vec<_> = body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|email| email["To"].as_str().unwrap())
+        .collect();
+    recipients.sort_unstable();
+    assert_eq!(
+        recipients,
+        ["octavia_butler@gmail.com", "ursula_le_guin@gmail.com"]
+    );
+    assert_eq!(app.issue_status().await?, "sent");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn newsletters_are_rendered_for_each_subscriber() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(AcceptBatch)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_newsletters(&newsletter_request_body()).await?;
+    app.dispatch_all_pending_emails().await?;
+
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_requests.last().unwrap().body)?;
+    let html_body = body[0]["HtmlBody"].as_str().unwrap();
+    let text_body = body[0]["TextBody"].as_str().unwrap();
+    assert!(html_body.contains("Hi le guin"));
+    assert!(html_body.contains("<p>Newsletter body as HTML</p>"));
+    assert!(html_body.contains("/subscriptions/unsubscribe?token="));
+    assert!(text_body.contains("Hi le guin"));
+    assert!(text_body.contains("Newsletter body as plain text"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn newsletters_can_be_written_in_markdown() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(AcceptBatch)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "markdown": "# Hello\n\nRead [the post](https://example.com) <script>alert(1)</script>",
+        }
+    });
+    let response = app.post_newsletters(&newsletter_request_body).await?;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    app.dispatch_all_pending_emails().await?;
+
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_requests.last().unwrap().body)?;
+    let html_body = body[0]["HtmlBody"].as_str().unwrap();
+    let text_body = body[0]["TextBody"].as_str().unwrap();
+    assert!(html_body.contains("<h1>Hello</h1>"));
+    assert!(html_body.contains(r#"<a href="https://example.com">the post</a>"#));
+    assert!(!html_body.contains("<script>"));
+    assert!(text_body.contains("Read the post (https://example.com)"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn newsletter_delivery_is_retried_after_a_transient_failure() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_newsletters(&newsletter_request_body()).await?;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    app.dispatch_all_pending_emails().await?;
+
+    let queued = sqlx::query!("SELECT n_retries FROM issue_delivery_queue")
+        .fetch_one(&app.db_pool)
+        .await?;
+    assert_eq!(queued.n_retries, 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn newsletter_delivery_honours_retry_after() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "3600"))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_newsletters(&newsletter_request_body()).await?;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    app.dispatch_all_pending_emails().await?;
+
+    let queued = sqlx::query!(
+        r#"
+        SELECT n_retries, execute_after > NOW() + INTERVAL '59 minutes' AS "waits!"
+        FROM issue_delivery_queue
+        "#
+    )
+    .fetch_one(&app.db_pool)
+    .await?;
+    assert_eq!(queued.n_retries, 1);
+    assert!(queued.waits);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn newsletter_delivery_is_not_retried_after_a_permanent_failure() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(422))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_newsletters(&newsletter_request_body()).await?;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+    app.dispatch_all_pending_emails().await?;
+
+    let queued = sqlx::query!("SELECT n_retries FROM issue_delivery_queue")
+        .fetch_optional(&app.db_pool)
+        .await?;
+    assert!(queued.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn newsletter_creation_is_idempotent() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    let idempotency_key = uuid::Uuid::new_v4().to_string();
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(AcceptBatch)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_newsletters_with_key(&newsletter_request_body(), &idempotency_key)
+        .await?;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    let response = app
+        .post_newsletters_with_key(&newsletter_request_body(), &idempotency_key)
+        .await?;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    app.dispatch_all_pending_emails().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn concurrent_newsletter_submissions_are_handled_gracefully() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    let idempotency_key = uuid::Uuid::new_v4().to_string();
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(AcceptBatch)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let body = newsletter_request_body();
+    let (response1, response2) = tokio::join!(
+        app.post_newsletters_with_key(&body, &idempotency_key),
+        app.post_newsletters_with_key(&body, &idempotency_key),
+    );
+    let (response1, response2) = (response1?, response2?);
+
+    assert_eq!(response1.status(), response2.status());
+    assert_eq!(response1.text().await?, response2.text().await?);
+
+    app.dispatch_all_pending_emails().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn newsletters_returns_422_for_invalid_data() -> Result<()> {
+    let app = spawn_app().await?;
+    let test_cases = vec![
+        (
+            serde_json::json!({
+                "content": {
+                    "text": "Newsletter body as plain text",
+                    "html": "<p>Newsletter body as HTML</p>",
+                }
+            }),
+            "missing title",
+        ),
+        (
+            serde_json::json!({"title": "Newsletter!"}),
+            "missing content",
+        ),
+        (
+            serde_json::json!({
+                "title": "Newsletter!",
+                "content": {"html": "<p>Newsletter body as HTML</p>"},
+            }),
+            "missing plain text content",
+        ),
+    ];
+
+    for (invalid_body, error_message) in test_cases {
+        let response = app.post_newsletters(&invalid_body).await?;
+
+        assert_eq!(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            response.status(),
+            "The API did not fail with 422 Unprocessable Entity when the payload was {error_message}.",
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn requests_missing_authorization_are_rejected() -> Result<()> {
+    let app = spawn_app().await?;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/newsletters", &app.address))
+        .json(&newsletter_request_body())
+        .send()
+        .await?;
+
+    assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+    assert_eq!(
+        r#"Basic realm="publish""#,
+        response.headers()["WWW-Authenticate"]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn non_existing_user_is_rejected() -> Result<()> {
+    let app = spawn_app().await?;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/newsletters", &app.address))
+        .basic_auth(
+            uuid::Uuid::new_v4().to_string(),
+            Some(&app.test_user.password),
+        )
+        .json(&newsletter_request_body())
+        .send()
+        .await?;
+
+    assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn invalid_password_is_rejected() -> Result<()> {
+    let app = spawn_app().await?;
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/newsletters", &app.address))
+        .basic_auth(&app.test_user.username, Some("not-the-password"))
+        .json(&newsletter_request_body())
+        .send()
+        .await?;
+
+    assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn the_configured_admin_is_seeded_and_can_publish() -> Result<()> {
+    let app = spawn_app().await?;
+    let admin = configuration::get()?.admin;
+    let password = admin.password.expect("no admin password is configured");
+
+    let response = reqwest::Client::new()
+        .post(format!("{}/newsletters", &app.address))
+        .basic_auth(&admin.username, Some(password.expose_secret()))
+        .json(&newsletter_request_body())
+        .send()
+        .await?;
+
+    assert_eq!(StatusCode::ACCEPTED, response.status());
+
+    Ok(())
+}
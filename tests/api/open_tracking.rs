@@ -0,0 +1,153 @@
+use anyhow::Result;
+use reqwest::StatusCode;
+
+use crate::helpers::{TestApp, create_confirmed_subscriber, spawn_app};
+
+struct Opens {
+    n_opens: i32,
+    opened: bool,
+}
+
+async fn opens(app: &TestApp) -> Result<Opens> {
+    Ok(sqlx::query_as!(
+        Opens,
+        r#"SELECT n_opens, first_opened_at IS NOT NULL AS "opened!" FROM deliveries"#
+    )
+    .fetch_one(&app.db_pool)
+    .await?)
+}
+
+#[tokio::test]
+async fn issues_do_not_track_opens_by_default() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+
+    let html = app.deliver_newsletter(&serde_json::json!({})).await?.html;
+
+    assert!(!html.contains("/t/o/"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn loading_the_pixel_records_an_open() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    let html = app
+        .deliver_newsletter(&serde_json::json!({ "track_opens": true }))
+        .await?
+        .html;
+
+    let response = reqwest::get(app.get_link(&html, "/t/o/")).await?;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["Content-Type"], "image/gif");
+    assert!(response.bytes().await?.starts_with(b"GIF89a"));
+    let opens = opens(&app).await?;
+    assert!(opens.opened);
+    assert_eq!(opens.n_opens, 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn repeat_opens_are_counted_but_keep_the_first_open_time() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    let html = app
+        .deliver_newsletter(&serde_json::json!({ "track_opens": true }))
+        .await?
+        .html;
+    let pixel_link = app.get_link(&html, "/t/o/");
+
+    reqwest::get(pixel_link.clone()).await?.error_for_status()?;
+    let first_opened_at = sqlx::query!("SELECT first_opened_at FROM deliveries")
+        .fetch_one(&app.db_pool)
+        .await?
+        .first_opened_at;
+    reqwest::get(pixel_link).await?.error_for_status()?;
+
+    let delivery = sqlx::query!("SELECT first_opened_at, n_opens FROM deliveries")
+        .fetch_one(&app.db_pool)
+        .await?;
+    assert_eq!(delivery.n_opens, 2);
+    assert_eq!(delivery.first_opened_at, first_opened_at);
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_forged_tracking_token_gets_the_pixel_but_records_nothing() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    let html = app
+        .deliver_newsletter(&serde_json::json!({ "track_opens": true }))
+        .await?
+        .html;
+    let mut pixel_link = app.get_link(&html, "/t/o/");
+    let forged = pixel_link.path().replacen("/t/o/", "/t/o/x", 1);
+    pixel_link.set_path(&forged);
+
+    let response = reqwest::get(pixel_link).await?;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["Content-Type"], "image/gif");
+    let opens = opens(&app).await?;
+    assert!(!opens.opened);
+    assert_eq!(opens.n_opens, 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn tracking_urls_must_end_in_gif() -> Result<()> {
+    let app = spawn_app().await?;
+
+    let response = reqwest::get(format!("{}/t/o/token.png", app.address)).await?;
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    Ok(())
+}
+
+#[tokio::test]
+async fn the_deliveries_page_shows_the_open_rate() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    let html = app
+        .deliver_newsletter(&serde_json::json!({ "track_opens": true }))
+        .await?
+        .html;
+    reqwest::get(app.get_link(&html, "/t/o/"))
+        .await?
+        .error_for_status()?;
+    app.login().await?;
+
+    let issue = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await?;
+    let html_page = app
+        .get_issue_deliveries(&format!("/admin/issues/{}", issue.newsletter_issue_id))
+        .await?
+        .text()
+        .await?;
+
+    assert!(html_page.contains("<tr><th>Opened</th><td>1</td></tr>"));
+    assert!(html_page.contains("<tr><th>Open rate</th><td>100.0%</td></tr>"));
+    assert!(html_page.contains("<tr><th>Total opens</th><td>1</td></tr>"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn drafts_can_opt_in_to_open_tracking() -> Result<()> {
+    let app = spawn_app().await?;
+    app.login().await?;
+
+    let response = app
+        .post_admin_issue(&serde_json::json!({
+            "title": "Issue #1",
+            "markdown": "Some *news*.",
+            "track_opens": true,
+        }))
+        .await?;
+    let issue_path = response.headers()["Location"].to_str()?.to_owned();
+
+    let html_page = app.get_admin_issue(&issue_path).await?.text().await?;
+    assert!(html_page.contains(r#"name="track_opens" value="true" checked"#));
+    Ok(())
+}
@@ -0,0 +1,162 @@
+use anyhow::Result;
+use chrono::{TimeDelta, Utc};
+use reqwest::StatusCode;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{
+    AcceptBatch, FakeClock, TestApp, assert_is_redirect_to, create_confirmed_subscriber, spawn_app,
+};
+
+fn in_hours(hours: i64) -> String {
+    (Utc::now() + TimeDelta::hours(hours))
+        .format("%Y-%m-%dT%H:%M:%SZ")
+        .to_string()
+}
+
+/// Logs in, creates a draft and schedules it `hours` from now.
+async fn schedule_draft(app: &TestApp, hours: i64) -> Result<String> {
+    app.login().await?;
+    let issue_path = app.create_draft_issue().await?;
+    let response = app
+        .post_schedule_issue(
+            &issue_path,
+            &serde_json::json!({"scheduled_for": in_hours(hours)}),
+        )
+        .await?;
+    assert_is_redirect_to(&response, &issue_path);
+    Ok(issue_path)
+}
+
+#[tokio::test]
+async fn scheduled_issues_are_not_sent_before_they_are_due() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    let clock = FakeClock::starting_now();
+    schedule_draft(&app, 1).await?;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    clock.advance(TimeDelta::minutes(59));
+    app.run_scheduler(&clock).await?;
+    app.dispatch_all_pending_emails().await?;
+
+    assert_eq!(app.issue_status().await?, "scheduled");
+    Ok(())
+}
+
+#[tokio::test]
+async fn scheduled_issues_are_delivered_once_they_are_due() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    let clock = FakeClock::starting_now();
+    schedule_draft(&app, 1).await?;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(AcceptBatch)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    clock.advance(TimeDelta::minutes(61));
+    app.run_scheduler(&clock).await?;
+    assert_eq!(app.issue_status().await?, "sending");
+    app.dispatch_all_pending_emails().await?;
+
+    assert_eq!(app.issue_status().await?, "sent");
+    Ok(())
+}
+
+#[tokio::test]
+async fn cancelled_issues_go_back_to_being_drafts() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    let clock = FakeClock::starting_now();
+    let issue_path = schedule_draft(&app, 1).await?;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_cancel_issue(&issue_path).await?;
+    assert_is_redirect_to(&response, &issue_path);
+
+    clock.advance(TimeDelta::hours(2));
+    app.run_scheduler(&clock).await?;
+    app.dispatch_all_pending_emails().await?;
+
+    assert_eq!(app.issue_status().await?, "draft");
+    Ok(())
+}
+
+#[tokio::test]
+async fn rescheduled_issues_wait_for_the_new_time() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    let clock = FakeClock::starting_now();
+    let issue_path = schedule_draft(&app, 1).await?;
+
+    let response = app
+        .post_schedule_issue(
+            &issue_path,
+            &serde_json::json!({"scheduled_for": in_hours(3)}),
+        )
+        .await?;
+    assert_is_redirect_to(&response, &issue_path);
+
+    clock.advance(TimeDelta::hours(2));
+    app.run_scheduler(&clock).await?;
+    assert_eq!(app.issue_status().await?, "scheduled");
+
+    clock.advance(TimeDelta::hours(2));
+    app.run_scheduler(&clock).await?;
+    assert_eq!(app.issue_status().await?, "sending");
+    Ok(())
+}
+
+#[tokio::test]
+async fn issues_cannot_be_scheduled_in_the_past() -> Result<()> {
+    let app = spawn_app().await?;
+    app.login().await?;
+    let issue_path = app.create_draft_issue().await?;
+
+    let response = app
+        .post_schedule_issue(
+            &issue_path,
+            &serde_json::json!({"scheduled_for": in_hours(-1)}),
+        )
+        .await?;
+    assert_is_redirect_to(&response, &issue_path);
+
+    let html_page = app.get_admin_issue(&issue_path).await?.text().await?;
+    assert!(html_page.contains("<p><i>The delivery time must be in the future.</i></p>"));
+    assert_eq!(app.issue_status().await?, "draft");
+    Ok(())
+}
+
+#[tokio::test]
+async fn sent_issues_cannot_be_scheduled_or_cancelled() -> Result<()> {
+    let app = spawn_app().await?;
+    app.login().await?;
+    let issue_path = app.create_draft_issue().await?;
+    app.post_publish_issue(&issue_path).await?;
+
+    let response = app
+        .post_schedule_issue(
+            &issue_path,
+            &serde_json::json!({"scheduled_for": in_hours(1)}),
+        )
+        .await?;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = app.post_cancel_issue(&issue_path).await?;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    Ok(())
+}
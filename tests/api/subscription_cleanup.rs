@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use bulletin::subscription_cleanup_worker::purge_stale_subscriptions;
+
+use crate::helpers::{
+    TestApp, create_confirmed_subscriber, create_unconfirmed_subscriber, spawn_app,
+};
+
+const TTL: Duration = Duration::from_hours(24);
+
+async fn count_rows(app: &TestApp) -> Result<(i64, i64)> {
+    let subscribers = sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await?;
+    let tokens = sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM subscription_tokens"#)
+        .fetch_one(&app.db_pool)
+        .await?;
+    Ok((subscribers.count, tokens.count))
+}
+
+#[tokio::test]
+async fn fresh_pending_subscribers_are_kept() -> Result<()> {
+    let app = spawn_app().await?;
+    create_unconfirmed_subscriber(&app).await?;
+
+    purge_stale_subscriptions(&app.db_pool, TTL).await?;
+
+    assert_eq!(count_rows(&app).await?, (1, 1));
+    Ok(())
+}
+
+#[tokio::test]
+async fn consumed_tokens_are_purged_but_confirmed_subscribers_are_kept() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    sqlx::query!("UPDATE subscriptions SET subscribed_at = NOW() - INTERVAL '1 year'")
+        .execute(&app.db_pool)
+        .await?;
+
+    purge_stale_subscriptions(&app.db_pool, TTL).await?;
+
+    assert_eq!(count_rows(&app).await?, (1, 0));
+    Ok(())
+}
+
+#[tokio::test]
+async fn never_confirmed_subscribers_are_purged_once_their_token_expires() -> Result<()> {
+    let app = spawn_app().await?;
+    create_unconfirmed_subscriber(&app).await?;
+    sqlx::query!("UPDATE subscriptions SET subscribed_at = NOW() - INTERVAL '1 year'")
+        .execute(&app.db_pool)
+        .await?;
+    sqlx::query!("UPDATE subscription_tokens SET created_at = NOW() - INTERVAL '1 year'")
+        .execute(&app.db_pool)
+        .await?;
+
+    purge_stale_subscriptions(&app.db_pool, TTL).await?;
+
+    assert_eq!(count_rows(&app).await?, (0, 0));
+    Ok(())
+}
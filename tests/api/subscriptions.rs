@@ -1,9 +1,10 @@
 use anyhow::Result;
+use bulletin::domain::{SubscriptionStatus, UnsubscribeToken};
 use reqwest::{Method, StatusCode};
-use wiremock::matchers::{method, path};
+use wiremock::matchers::{any, method, path};
 use wiremock::{Mock, ResponseTemplate};
 
-use crate::helpers::spawn_app;
+use crate::helpers::{create_confirmed_subscriber, spawn_app};
 
 #[tokio::test]
 async fn subscribe_returns_a_200_for_valid_form_data() -> Result<()> {
@@ -37,14 +38,16 @@ async fn subscribe_persists_the_new_subscriber() -> Result<()> {
 
     app.post_subscriptions(body).await?;
 
-    let saved = sqlx::query!("SELECT email, name, status FROM subscriptions",)
-        .fetch_one(&app.db_pool)
-        .await
-        .expect("Failed to fetch saved subscription.");
+    let saved = sqlx::query!(
+        r#"SELECT email, name, status AS "status: SubscriptionStatus" FROM subscriptions"#,
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to fetch saved subscription.");
 
     assert_eq!(saved.email, "ursula_le_guin@gmail.com");
     assert_eq!(saved.name, "le guin");
-    assert_eq!(saved.status, "pending_confirmation");
+    assert_eq!(saved.status, SubscriptionStatus::PendingConfirmation);
 
     Ok(())
 }
@@ -149,3 +152,283 @@ async fn subscribe_fails_if_there_is_a_fatal_database_error() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn subscribe_keeps_the_confirmation_email_if_the_email_provider_is_unavailable() -> Result<()>
+{
+    let app = spawn_app().await?;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_subscriptions(body).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let queued = sqlx::query!("SELECT n_retries FROM email_outbox")
+        .fetch_one(&app.db_pool)
+        .await?;
+    assert_eq!(queued.n_retries, 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn subscribe_does_not_persist_the_subscriber_if_the_email_cannot_be_queued() -> Result<()> {
+    let app = spawn_app().await?;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    sqlx::query!("ALTER TABLE email_outbox DROP COLUMN subject")
+        .execute(&app.db_pool)
+        .await?;
+
+    let response = app.subscriptions_request(body).send().await?;
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+    let saved = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await?;
+    assert_eq!(saved.count, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn subscribe_is_idempotent() -> Result<()> {
+    let app = spawn_app().await?;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    let idempotency_key = uuid::Uuid::new_v4().to_string();
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_subscriptions_with_key(body, &idempotency_key)
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .post_subscriptions_with_key(body, &idempotency_key)
+        .await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let saved = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await?;
+    assert_eq!(saved.count, 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn reusing_an_idempotency_key_for_another_sign_up_is_rejected() -> Result<()> {
+    let app = spawn_app().await?;
+    let idempotency_key = uuid::Uuid::new_v4().to_string();
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions_with_key(
+        "name=le%20guin&email=ursula_le_guin%40gmail.com",
+        &idempotency_key,
+    )
+    .await?
+    .error_for_status()?;
+    let response = app
+        .post_subscriptions_with_key(
+            "name=tolkien&email=jrr_tolkien%40gmail.com",
+            &idempotency_key,
+        )
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let saved = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_all(&app.db_pool)
+        .await?;
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].email, "ursula_le_guin@gmail.com");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn subscribe_returns_a_400_for_an_invalid_idempotency_key() -> Result<()> {
+    let app = spawn_app().await?;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    let response = app
+        .post_subscriptions_with_key(body, &"a".repeat(50))
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn subscribing_twice_while_pending_does_not_resend_immediately() -> Result<()> {
+    let app = spawn_app().await?;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_subscriptions(body).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.post_subscriptions(body).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn subscribing_again_while_pending_rotates_the_confirmation_token() -> Result<()> {
+    let app = spawn_app().await?;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions(body).await?.error_for_status()?;
+    sqlx::query!("UPDATE subscription_tokens SET created_at = NOW() - INTERVAL '1 hour'")
+        .execute(&app.db_pool)
+        .await?;
+    let response = app.post_subscriptions(body).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    let old_links = app.get_confirmation_links(&email_requests[0])?;
+    let new_links = app.get_confirmation_links(&email_requests[1])?;
+    assert_ne!(old_links.html, new_links.html);
+
+    let response = reqwest::get(old_links.html).await?;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = reqwest::get(new_links.html).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn subscribing_with_a_confirmed_email_succeeds_without_sending_an_email() -> Result<()> {
+    let app = spawn_app().await?;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    create_confirmed_subscriber(&app).await?;
+
+    Mock::given(path("/email"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_subscriptions(body).await?;
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn concurrent_first_sign_ups_with_the_same_email_both_succeed() -> Result<()> {
+    let app = spawn_app().await?;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let (response1, response2) =
+        tokio::join!(app.post_subscriptions(body), app.post_subscriptions(body));
+
+    assert_eq!(response1?.status(), StatusCode::OK);
+    assert_eq!(response2?.status(), StatusCode::OK);
+    let saved = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await?;
+    assert_eq!(saved.count, 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn subscribers_who_unsubscribed_can_sign_up_again() -> Result<()> {
+    let app = spawn_app().await?;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    create_confirmed_subscriber(&app).await?;
+    let subscriber = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await?;
+    let token = UnsubscribeToken::new(subscriber.id, &app.hmac_secret);
+    app.post_unsubscribe(format!(
+        "{}/subscriptions/unsubscribe?token={}",
+        app.address,
+        token.as_ref()
+    ))
+    .await?
+    .error_for_status()?;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_subscriptions(body).await?;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_links = app.get_confirmation_links(&email_request)?;
+    reqwest::get(confirmation_links.html)
+        .await?
+        .error_for_status()?;
+
+    let saved = sqlx::query!(
+        r#"
+        SELECT
+            status AS "status: SubscriptionStatus",
+            NOT EXISTS (SELECT 1 FROM suppressions) AS "unsuppressed!"
+        FROM subscriptions
+        "#
+    )
+    .fetch_one(&app.db_pool)
+    .await?;
+    assert_eq!(saved.status, SubscriptionStatus::Confirmed);
+    assert!(saved.unsuppressed);
+
+    Ok(())
+}
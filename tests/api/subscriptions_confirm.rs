@@ -1,9 +1,10 @@
 use anyhow::Result;
+use bulletin::domain::SubscriptionStatus;
 use reqwest::StatusCode;
 use wiremock::matchers::{method, path};
 use wiremock::{Mock, ResponseTemplate};
 
-use crate::helpers::spawn_app;
+use crate::helpers::{create_unconfirmed_subscriber, spawn_app};
 
 #[tokio::test]
 async fn confirmations_without_token_are_rejected_with_a_400() -> Result<()> {
@@ -16,6 +17,22 @@ async fn confirmations_without_token_are_rejected_with_a_400() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn unknown_tokens_do_not_ask_for_credentials() -> Result<()> {
+    let app = spawn_app().await?;
+
+    let response = reqwest::get(&format!(
+        "{}/subscriptions/confirm?subscription_token=unknown",
+        app.address
+    ))
+    .await?;
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert!(!response.headers().contains_key("WWW-Authenticate"));
+
+    Ok(())
+}
+
 #[tokio::test]
 async fn the_link_returned_by_subscribe_returns_a_200_if_called() -> Result<()> {
     let app = spawn_app().await?;
@@ -57,13 +74,49 @@ async fn clicking_on_the_confirmation_link_confirms_a_subscriber() -> Result<()>
         .await?
         .error_for_status()?;
 
-    let saved = sqlx::query!("SELECT email, name, status FROM subscriptions",)
-        .fetch_one(&app.db_pool)
-        .await?;
+    let saved = sqlx::query!(
+        r#"SELECT email, name, status AS "status: SubscriptionStatus" FROM subscriptions"#,
+    )
+    .fetch_one(&app.db_pool)
+    .await?;
 
     assert_eq!(saved.email, "ursula_le_guin@gmail.com");
     assert_eq!(saved.name, "le guin");
-    assert_eq!(saved.status, "confirmed");
+    assert_eq!(saved.status, SubscriptionStatus::Confirmed);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_confirmation_link_cannot_be_used_twice() -> Result<()> {
+    let app = spawn_app().await?;
+    let confirmation_links = create_unconfirmed_subscriber(&app).await?;
+
+    reqwest::get(confirmation_links.html.clone())
+        .await?
+        .error_for_status()?;
+    let response = reqwest::get(confirmation_links.html).await?;
+
+    assert_eq!(response.status(), StatusCode::GONE);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn an_expired_confirmation_link_is_rejected() -> Result<()> {
+    let app = spawn_app().await?;
+    let confirmation_links = create_unconfirmed_subscriber(&app).await?;
+    sqlx::query!("UPDATE subscription_tokens SET created_at = NOW() - INTERVAL '1 year'")
+        .execute(&app.db_pool)
+        .await?;
+
+    let response = reqwest::get(confirmation_links.html).await?;
+
+    assert_eq!(response.status(), StatusCode::GONE);
+    let saved = sqlx::query!(r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await?;
+    assert_eq!(saved.status, SubscriptionStatus::PendingConfirmation);
 
     Ok(())
 }
@@ -0,0 +1,189 @@
+use anyhow::Result;
+use bulletin::domain::{SubscriptionStatus, UnsubscribeToken};
+use reqwest::StatusCode;
+use wiremock::Mock;
+use wiremock::matchers::path;
+
+use crate::helpers::{
+    AcceptBatch, TestApp, create_confirmed_subscriber, create_unconfirmed_subscriber, spawn_app,
+};
+
+async fn subscriber_status(app: &TestApp) -> Result<SubscriptionStatus> {
+    let saved = sqlx::query!(r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await?;
+    Ok(saved.status)
+}
+
+#[tokio::test]
+async fn newsletters_advertise_one_click_unsubscribe() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    app.deliver_newsletter(&serde_json::json!({})).await?;
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body)?;
+    let headers = body[0]["Headers"].as_array().unwrap();
+
+    assert!(headers.iter().any(|h| {
+        h["Name"] == "List-Unsubscribe-Post" && h["Value"] == "List-Unsubscribe=One-Click"
+    }));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn clicking_on_the_unsubscribe_link_only_asks_for_confirmation() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    let unsubscribe_link = app
+        .deliver_newsletter(&serde_json::json!({}))
+        .await?
+        .unsubscribe_link;
+
+    let response = reqwest::get(unsubscribe_link).await?;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let html_page = response.text().await?;
+    assert!(html_page.contains(r#"<form action="/subscriptions/unsubscribe?token="#));
+    assert!(html_page.contains(r#"method="post""#));
+    assert_eq!(
+        subscriber_status(&app).await?,
+        SubscriptionStatus::Confirmed
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn confirming_on_the_unsubscribe_page_unsubscribes_a_subscriber() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    let unsubscribe_link = app
+        .deliver_newsletter(&serde_json::json!({}))
+        .await?
+        .unsubscribe_link;
+
+    let response = app.post_unsubscribe(unsubscribe_link).await?;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        subscriber_status(&app).await?,
+        SubscriptionStatus::Unsubscribed
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn one_click_unsubscribe_unsubscribes_a_subscriber() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    let unsubscribe_link = app
+        .deliver_newsletter(&serde_json::json!({}))
+        .await?
+        .unsubscribe_link;
+
+    let response = reqwest::Client::new()
+        .post(unsubscribe_link)
+        .form(&[("List-Unsubscribe", "One-Click")])
+        .send()
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        subscriber_status(&app).await?,
+        SubscriptionStatus::Unsubscribed
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unsubscribed_subscribers_do_not_receive_newsletters() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    let unsubscribe_link = app
+        .deliver_newsletter(&serde_json::json!({}))
+        .await?
+        .unsubscribe_link;
+    app.post_unsubscribe(unsubscribe_link)
+        .await?
+        .error_for_status()?;
+
+    Mock::given(path("/email/batch"))
+        .respond_with(AcceptBatch)
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_newsletters(&serde_json::json!({
+        "title": "Another newsletter",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>",
+        }
+    }))
+    .await?
+    .error_for_status()?;
+    app.dispatch_all_pending_emails().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_forged_unsubscribe_token_is_rejected() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    let mut unsubscribe_link = app
+        .deliver_newsletter(&serde_json::json!({}))
+        .await?
+        .unsubscribe_link;
+    unsubscribe_link.set_query(Some(&format!("token={}.forged", uuid::Uuid::new_v4())));
+
+    let response = reqwest::get(unsubscribe_link.clone()).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = app.post_unsubscribe(unsubscribe_link).await?;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    assert_eq!(
+        subscriber_status(&app).await?,
+        SubscriptionStatus::Confirmed
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_subscriber_who_unsubscribed_before_confirming_cannot_confirm() -> Result<()> {
+    let app = spawn_app().await?;
+    let confirmation_links = create_unconfirmed_subscriber(&app).await?;
+    let subscriber = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await?;
+    let token = UnsubscribeToken::new(subscriber.id, &app.hmac_secret);
+    let unsubscribe_link = format!(
+        "{}/subscriptions/unsubscribe?token={}",
+        app.address,
+        token.as_ref()
+    );
+    app.post_unsubscribe(unsubscribe_link)
+        .await?
+        .error_for_status()?;
+
+    let response = reqwest::get(confirmation_links.html).await?;
+
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+    assert_eq!(
+        subscriber_status(&app).await?,
+        SubscriptionStatus::Unsubscribed
+    );
+
+    Ok(())
+}
@@ -0,0 +1,278 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use bulletin::domain::{SubscriberEmail, SuppressionReason, UnsubscribeToken};
+use bulletin::email_client::{
+    EmailError, EmailHeader, EmailSender, OutgoingEmail, SuppressingSender,
+};
+use reqwest::StatusCode;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+use crate::helpers::{TestApp, assert_is_redirect_to, create_confirmed_subscriber, spawn_app};
+
+const SUBSCRIBER_EMAIL: &str = "ursula_le_guin@gmail.com";
+
+struct Suppression {
+    address: String,
+    reason: SuppressionReason,
+    source: String,
+}
+
+/// A backend that loses the results of every batch it sends.
+#[derive(Debug)]
+struct ShortBatchSender;
+
+#[async_trait]
+impl EmailSender for ShortBatchSender {
+    async fn send_email(
+        &self,
+        _recipient: SubscriberEmail,
+        _subject: &str,
+        _html_content: &str,
+        _text_content: &str,
+        _headers: &[EmailHeader],
+    ) -> Result<Option<String>, EmailError> {
+        Ok(None)
+    }
+
+    async fn send_batch(
+        &self,
+        _emails: &[OutgoingEmail],
+    ) -> Result<Vec<Result<Option<String>, EmailError>>, EmailError> {
+        Ok(Vec::new())
+    }
+}
+
+async fn suppressions(app: &TestApp) -> Result<Vec<Suppression>> {
+    Ok(sqlx::query_as!(
+        Suppression,
+        r#"
+        SELECT address, reason AS "reason: SuppressionReason", source
+        FROM suppressions
+        ORDER BY address
+        "#,
+    )
+    .fetch_all(&app.db_pool)
+    .await?)
+}
+
+async fn publish_newsletter(app: &TestApp) -> Result<()> {
+    app.post_newsletters(&serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>",
+        }
+    }))
+    .await?
+    .error_for_status()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_suppression_list() -> Result<()> {
+    let app = spawn_app().await?;
+
+    let response = app.get_suppressions().await?;
+
+    assert_is_redirect_to(&response, "/login");
+    Ok(())
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_suppress_an_address() -> Result<()> {
+    let app = spawn_app().await?;
+
+    let response = app.post_suppression(SUBSCRIBER_EMAIL).await?;
+
+    assert_is_redirect_to(&response, "/login");
+    assert!(suppressions(&app).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn admins_can_suppress_an_address() -> Result<()> {
+    let app = spawn_app().await?;
+    app.login().await?;
+
+    let response = app.post_suppression("Ursula_Le_Guin@gmail.com").await?;
+    assert_is_redirect_to(&response, "/admin/suppressions");
+
+    let html_page = app.get_suppressions_html().await?;
+    assert!(
+        html_page.contains("<p><i>ursula_le_guin@gmail.com will no longer be emailed.</i></p>")
+    );
+    assert!(html_page.contains(SUBSCRIBER_EMAIL));
+
+    let saved = suppressions(&app).await?;
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].address, SUBSCRIBER_EMAIL);
+    assert_eq!(saved[0].reason, SuppressionReason::Manual);
+    assert_eq!(saved[0].source, app.test_user.username);
+    Ok(())
+}
+
+#[tokio::test]
+async fn an_invalid_address_cannot_be_suppressed() -> Result<()> {
+    let app = spawn_app().await?;
+    app.login().await?;
+
+    let response = app.post_suppression("not-an-email").await?;
+    assert_is_redirect_to(&response, "/admin/suppressions");
+
+    let html_page = app.get_suppressions_html().await?;
+    assert!(html_page.contains("<p><i>That is not a valid email address.</i></p>"));
+    assert!(suppressions(&app).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn admins_can_lift_a_suppression() -> Result<()> {
+    let app = spawn_app().await?;
+    app.login().await?;
+    app.post_suppression(SUBSCRIBER_EMAIL).await?;
+
+    let response = app.post_lift_suppression(SUBSCRIBER_EMAIL).await?;
+    assert_is_redirect_to(&response, "/admin/suppressions");
+
+    let html_page = app.get_suppressions_html().await?;
+    assert!(html_page.contains("<p><i>ursula_le_guin@gmail.com can be emailed again.</i></p>"));
+    assert!(suppressions(&app).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn lifting_an_address_that_is_not_suppressed_is_reported() -> Result<()> {
+    let app = spawn_app().await?;
+    app.login().await?;
+
+    app.post_lift_suppression(SUBSCRIBER_EMAIL).await?;
+
+    let html_page = app.get_suppressions_html().await?;
+    assert!(
+        html_page
+            .contains("<p><i>ursula_le_guin@gmail.com is not on the suppression list.</i></p>")
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn suppressed_addresses_do_not_receive_a_confirmation_email() -> Result<()> {
+    let app = spawn_app().await?;
+    app.login().await?;
+    app.post_suppression(SUBSCRIBER_EMAIL).await?;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com")
+        .await?;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    Ok(())
+}
+
+#[tokio::test]
+async fn suppressed_subscribers_are_skipped_when_an_issue_is_delivered() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    app.login().await?;
+    app.post_suppression(SUBSCRIBER_EMAIL).await?;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter(&app).await?;
+    app.dispatch_all_pending_emails().await?;
+
+    let delivery = sqlx::query!(r#"SELECT status::TEXT AS "status!", last_error FROM deliveries"#)
+        .fetch_one(&app.db_pool)
+        .await?;
+    assert_eq!(delivery.status, "skipped");
+    assert_eq!(
+        delivery.last_error.as_deref(),
+        Some("the address is on the suppression list")
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn unsubscribing_suppresses_the_address() -> Result<()> {
+    let app = spawn_app().await?;
+    create_confirmed_subscriber(&app).await?;
+    let subscriber = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await?;
+    let token = UnsubscribeToken::new(subscriber.id, &app.hmac_secret);
+
+    app.post_unsubscribe(format!(
+        "{}/subscriptions/unsubscribe?token={}",
+        app.address,
+        token.as_ref()
+    ))
+    .await?
+    .error_for_status()?;
+
+    let saved = suppressions(&app).await?;
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].address, SUBSCRIBER_EMAIL);
+    assert_eq!(saved[0].reason, SuppressionReason::Unsubscribe);
+    assert_eq!(saved[0].source, "unsubscribe link");
+    Ok(())
+}
+
+#[tokio::test]
+async fn bounces_suppress_the_address_even_without_a_subscription() -> Result<()> {
+    let app = spawn_app().await?;
+
+    app.post_postmark_webhook(&serde_json::json!({
+        "RecordType": "Bounce",
+        "Type": "HardBounce",
+        "Email": "Someone@Example.com",
+        "Inactive": true,
+    }))
+    .await?
+    .error_for_status()?;
+
+    let saved = suppressions(&app).await?;
+    assert_eq!(saved.len(), 1);
+    assert_eq!(saved[0].address, "someone@example.com");
+    assert_eq!(saved[0].reason, SuppressionReason::Bounce);
+    assert_eq!(saved[0].source, "postmark");
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_batch_missing_results_fails_as_a_whole() -> Result<()> {
+    let app = spawn_app().await?;
+    let email_client = SuppressingSender::new(Arc::new(ShortBatchSender), app.db_pool.clone());
+    let email = OutgoingEmail {
+        recipient: SubscriberEmail::parse(SUBSCRIBER_EMAIL.to_owned()).unwrap(),
+        subject: "Subject".into(),
+        html_content: "<p>Body</p>".into(),
+        text_content: "Body".into(),
+        headers: Vec::new(),
+    };
+
+    let outcome = email_client.send_batch(&[email]).await;
+
+    assert!(matches!(
+        outcome,
+        Err(EmailError::BatchResults {
+            expected: 1,
+            got: 0
+        })
+    ));
+    Ok(())
+}